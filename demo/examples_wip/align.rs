@@ -1,11 +1,14 @@
 #![allow(dead_code)]
+extern crate stainless;
+use stainless::*;
 
+#[var(x, y)]
 pub struct Point {
   pub x: i32, pub y: i32
 }
 
-// #[ensures="p.x == old(p.x) + s"]
-// #[ensures="p.y == old(p.y)"]
+#[post(p.x == old(p.x) + s)]
+#[post(p.y == old(p.y))]
 fn shift_x(p: &mut Point, s: i32) {
   p.x = p.x + s
 }
@@ -1,4 +1,8 @@
+extern crate stainless;
+use stainless::*;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[var(0, 1)]
 struct Point(i32, i32);
 // Also, Point is "freeze" in rustc, i.e. no interior mutability
 
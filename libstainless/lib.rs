@@ -3,6 +3,85 @@ pub use stainless_macros::*;
 
 use std::marker::PhantomData;
 
+/// Loop invariant, stated at the beginning of a `while` or `loop` body:
+///
+/// ```ignore
+/// while i < n {
+///   loop_invariant!(i <= n);
+///   i += 1;
+/// }
+/// ```
+///
+/// The macro can't be called `invariant!`, since that name is taken by the `#[invariant]`
+/// attribute on structs and enums.
+#[macro_export]
+macro_rules! loop_invariant {
+  ($condition:expr) => {
+    $crate::invariant($condition)
+  };
+}
+
+#[doc(hidden)]
+pub fn invariant(_condition: bool) {}
+
+/// The value of a parameter, or of some of its fields, when the function was entered. This is
+/// only meaningful in postconditions of functions that mutate their arguments:
+///
+/// ```ignore
+/// #[post(p.x == old(p.x) + s)]
+/// fn shift_x(p: &mut Point, s: i32) {
+///   p.x = p.x + s;
+/// }
+/// ```
+///
+/// The specification macros borrow the argument of `old`, so that it also applies to fields
+/// that aren't `Copy`.
+pub fn old<T>(value: T) -> T {
+  value
+}
+
+/// Assumption of a fact in a function body, which Stainless takes for granted in the rest of the
+/// body without checking it. Unlike `assert!`, it never panics:
+///
+/// ```ignore
+/// assume!(x < 100);
+/// let y = x + 1;
+/// ```
+#[macro_export]
+macro_rules! assume {
+  ($fact:expr) => {
+    $crate::assume_fact($fact)
+  };
+}
+
+#[doc(hidden)]
+pub fn assume_fact(_fact: bool) {}
+
+/// Universal quantification over the parameters of a closure, for use in specifications. The
+/// closure's parameters need explicit types:
+///
+/// ```ignore
+/// #[post(forall(|i: u32| !(i < n) || ret > i))]
+/// fn above(n: u32) -> u32 {
+///   n
+/// }
+/// ```
+pub fn forall<Args, P: Predicate<Args>>(_predicate: P) -> bool {
+  unimplemented!()
+}
+
+/// Existential quantification over the parameters of a closure, the dual of `forall`.
+pub fn exists<Args, P: Predicate<Args>>(_predicate: P) -> bool {
+  unimplemented!()
+}
+
+/// Closures returning `bool`, taking one or more parameters
+pub trait Predicate<Args> {}
+
+impl<A, F: Fn(A) -> bool> Predicate<(A,)> for F {}
+impl<A, B, F: Fn(A, B) -> bool> Predicate<(A, B)> for F {}
+impl<A, B, C, F: Fn(A, B, C) -> bool> Predicate<(A, B, C)> for F {}
+
 #[derive(Copy, Clone, PartialEq)]
 pub struct Set<T> {
   phantom: PhantomData<T>,
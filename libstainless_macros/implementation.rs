@@ -1,6 +1,11 @@
-use proc_macro2::{Ident, Span, TokenStream};
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
 use quote::{format_ident, quote, ToTokens};
-use syn::{parse_quote, Attribute, Expr, FnArg, Item, ItemFn, Result, ReturnType, Stmt, Type};
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+  parse_quote, Attribute, Error, Expr, FnArg, Item, ItemFn, Result, ReturnType, Stmt, Type,
+};
 
 use std::convert::TryFrom;
 use std::iter;
@@ -93,7 +98,7 @@ fn try_parse(
 
 fn generate_fn_with_spec(mut item_fn: ItemFn, specs: Vec<Spec>) -> Vec<ItemFn> {
   let fn_generics = &item_fn.sig.generics;
-  let fn_arg_tys = &item_fn.sig.inputs;
+  let fn_arg_tys = spec_inputs(&item_fn.sig.inputs);
   let fn_return_ty: Type = match &item_fn.sig.output {
     ReturnType::Type(_, ty) => *ty.clone(),
     ReturnType::Default => parse_quote! { () },
@@ -105,12 +110,13 @@ fn generate_fn_with_spec(mut item_fn: ItemFn, specs: Vec<Spec>) -> Vec<ItemFn> {
     // For sibling specs, the name of the spec'ed function has to be given.
     let spec_ident = format_ident!("__{}_{}_{}", spec.typ.name(), index + 1, fn_name);
 
-    let ret_param: TokenStream = match spec.typ {
-      SpecType::Post => quote! { , ret: #fn_return_ty },
-      _ => quote! {},
-    };
+    let mut spec_params = fn_arg_tys.clone();
+    if spec.typ == SpecType::Post {
+      spec_params.push(parse_quote! { ret: #fn_return_ty });
+    }
 
-    let expr = spec.expr;
+    let mut expr = spec.expr;
+    BorrowOldValues.visit_expr_mut(&mut expr);
     let (return_type, body): (Type, TokenStream) = match spec.typ {
       SpecType::Measure => (parse_quote!(()), parse_quote! { #expr; }),
       _ => (parse_quote!(bool), parse_quote! { #expr }),
@@ -119,7 +125,7 @@ fn generate_fn_with_spec(mut item_fn: ItemFn, specs: Vec<Spec>) -> Vec<ItemFn> {
     parse_quote! {
       #[doc(hidden)]
       #[allow(unused_variables)]
-      fn #spec_ident#fn_generics(#fn_arg_tys#ret_param) -> #return_type {
+      fn #spec_ident#fn_generics(#spec_params) -> #return_type {
         #body
       }
     }
@@ -137,8 +143,10 @@ fn generate_fn_with_spec(mut item_fn: ItemFn, specs: Vec<Spec>) -> Vec<ItemFn> {
     })
     .unwrap_or(false);
 
-  // If the function has the 'self' param, then the specs must be siblings
-  if has_self_param {
+  // If the function has the 'self' param or mentions the 'Self' type, it is an
+  // associated function and the specs must be siblings, since nested functions
+  // can't refer to the surrounding impl.
+  if has_self_param || mentions_self_type(item_fn.sig.to_token_stream()) {
     specs.chain(iter::once(item_fn.clone())).collect()
   }
   // otherwise the specs can be nested
@@ -154,6 +162,64 @@ fn generate_fn_with_spec(mut item_fn: ItemFn, specs: Vec<Spec>) -> Vec<ItemFn> {
   }
 }
 
+/// The parameters of spec functions are those of the spec'ed function, except that mutable
+/// references become shared ones. This lets specs mention both a parameter and its `old` value,
+/// as in `#[post(p.x == old(p.x) + 1)]`, without running afoul of the borrow checker.
+fn spec_inputs(inputs: &Punctuated<FnArg, Comma>) -> Punctuated<FnArg, Comma> {
+  inputs
+    .iter()
+    .cloned()
+    .map(|input| match input {
+      FnArg::Receiver(mut receiver) => {
+        if receiver.reference.is_some() {
+          receiver.mutability = None;
+        }
+        FnArg::Receiver(receiver)
+      }
+      FnArg::Typed(mut pat_type) => {
+        if let Type::Reference(ref mut reference) = *pat_type.ty {
+          reference.mutability = None;
+        }
+        FnArg::Typed(pat_type)
+      }
+    })
+    .collect()
+}
+
+/// Rewrites `old(e)` in specs to `(*old(&e))`, so that the old values of fields that aren't
+/// `Copy` can be used without moving them out of the parameters. Extraction erases the
+/// reference and the dereference again.
+struct BorrowOldValues;
+
+impl VisitMut for BorrowOldValues {
+  fn visit_expr_mut(&mut self, expr: &mut Expr) {
+    visit_mut::visit_expr_mut(self, expr);
+    if let Expr::Call(call) = expr {
+      let is_old = match &*call.func {
+        Expr::Path(path) => path
+          .path
+          .segments
+          .last()
+          .map_or(false, |segment| segment.ident == "old"),
+        _ => false,
+      };
+      if is_old && call.args.len() == 1 {
+        let func = &call.func;
+        let arg = &call.args[0];
+        *expr = parse_quote!((*#func(&#arg)));
+      }
+    }
+  }
+}
+
+fn mentions_self_type(tokens: TokenStream) -> bool {
+  tokens.into_iter().any(|token| match token {
+    TokenTree::Ident(ident) => ident == "Self",
+    TokenTree::Group(group) => mentions_self_type(group.stream()),
+    _ => false,
+  })
+}
+
 /// Extract all the specs from a given function and insert spec functions
 pub fn extract_specs_and_expand(
   first_spec_type: SpecType,
@@ -178,6 +244,69 @@ pub fn extract_specs_and_expand(
   }
 }
 
+/// Invariants (of structs and enums)
+
+fn is_invariant_attr(attr: &Attribute) -> bool {
+  attr.path.is_ident("invariant")
+}
+
+/// Extract all the invariants from a given struct or enum and add an invariant function, which
+/// is a method of the type named `__invariant_1_{type_name}`, in line with spec functions.
+pub fn extract_invariants_and_expand(
+  first_attr_args: TokenStream,
+  item: TokenStream,
+) -> TokenStream {
+  let mut item: Item = parse_quote!(#item);
+  let (attrs, ident, generics) = match &mut item {
+    Item::Struct(item) => (&mut item.attrs, item.ident.clone(), item.generics.clone()),
+    Item::Enum(item) => (&mut item.attrs, item.ident.clone(), item.generics.clone()),
+    _ => {
+      return Error::new(
+        Span::call_site(),
+        "Invariants can only be attached to structs and enums",
+      )
+      .to_compile_error()
+    }
+  };
+
+  // Parse the first invariant and all the remaining ones, then remove their attributes
+  let first_invariant: Result<Expr> = syn::parse2(first_attr_args);
+  let invariants: Result<Vec<Expr>> = iter::once(first_invariant)
+    .chain(
+      attrs
+        .iter()
+        .filter(|attr| is_invariant_attr(attr))
+        .map(|attr| attr.parse_args()),
+    )
+    .collect();
+  let invariants = match invariants {
+    Ok(invariants) => invariants,
+    Err(err) => return err.to_compile_error(),
+  };
+  attrs.retain(|attr| !is_invariant_attr(attr));
+
+  // Conjoin all invariants
+  let mut invariants = invariants
+    .into_iter()
+    .map(|expr| -> Expr { parse_quote!((#expr)) });
+  let first = invariants.next().unwrap();
+  let body = invariants.fold(first, |acc, expr| parse_quote!(#acc && #expr));
+
+  let invariant_ident = format_ident!("__invariant_1_{}", ident);
+  let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+  quote! {
+    #item
+
+    impl#impl_generics #ident#ty_generics #where_clause {
+      #[doc(hidden)]
+      #[allow(dead_code, non_snake_case)]
+      fn #invariant_ident(&self) -> bool {
+        #body
+      }
+    }
+  }
+}
+
 /// Flags
 
 /// Note that we simply want to attach some attributes to the item in question. Currently,
@@ -21,6 +21,12 @@ pub fn measure(attr: TokenStream, item: TokenStream) -> TokenStream {
   extract_specs_and_expand(SpecType::Measure, attr.into(), item.into()).into()
 }
 
+/// Invariant of a struct or enum, which has to hold for every value of the type
+#[proc_macro_attribute]
+pub fn invariant(attr: TokenStream, item: TokenStream) -> TokenStream {
+  extract_invariants_and_expand(attr.into(), item.into()).into()
+}
+
 /// Flags
 
 macro_rules! define_flags {
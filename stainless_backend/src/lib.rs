@@ -2,18 +2,36 @@ extern crate serde;
 extern crate serde_json;
 
 use std::env;
-use std::io::{LineWriter, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command, Stdio};
+use std::process::Command;
 use std::result::Result;
+use std::time::Duration;
 
 use stainless_data::ast as st;
 
 pub mod messages;
+pub mod mock;
+pub mod pool;
+mod process;
 use messages::{Report, Response};
+use mock::MockBackend;
+use pool::BackendPool;
+use process::Process;
 
+/// Names a file of canned responses to replay instead of running Stainless
+const MOCK_RESPONSES_VAR: &str = "STAINLESS_MOCK_RESPONSES";
+
+/// Something that verifies Stainless programs, like the Stainless process
+pub trait VerifierBackend {
+  fn query_for_program(&mut self, symbols: &st::Symbols) -> Result<Response, String>;
+}
+
+#[derive(Clone)]
 pub struct Config {
   timeout: usize,
+  query_timeout: Option<Duration>,
+  processes: usize,
   print_ids: bool,
   print_types: bool,
   debug_trees: bool,
@@ -25,6 +43,8 @@ impl Default for Config {
   fn default() -> Self {
     Self {
       timeout: 30,
+      query_timeout: Some(Duration::from_secs(600)),
+      processes: 1,
       print_ids: false,
       print_types: false,
       debug_trees: false,
@@ -34,14 +54,49 @@ impl Default for Config {
   }
 }
 
-#[allow(dead_code)]
+impl Config {
+  /// Whether Stainless checks arithmetic operations for overflows, like Rust does in debug builds
+  pub fn with_strict_arithmetic(self, strict_arithmetic: bool) -> Self {
+    Self {
+      strict_arithmetic,
+      ..self
+    }
+  }
+
+  /// How many Stainless processes to verify programs with in parallel
+  pub fn with_processes(self, processes: usize) -> Self {
+    Self {
+      processes: processes.max(1),
+      ..self
+    }
+  }
+
+  /// How long to wait for Stainless to answer a query before giving up on it and restarting it
+  pub fn with_query_timeout(self, query_timeout: Option<Duration>) -> Self {
+    Self {
+      query_timeout,
+      ..self
+    }
+  }
+}
+
+/// A backend running Stainless in interactive mode, which answers queries until it's dropped.
+/// Stainless is restarted whenever it crashes or fails to answer a query in time.
 pub struct Backend {
   config: Config,
-  child: Child,
+  process: Option<Process>,
 }
 
 impl Backend {
   pub fn create(config: Config) -> Result<Self, String> {
+    let process = Process::spawn(Self::command(&config)?)?;
+    Ok(Self {
+      config,
+      process: Some(process),
+    })
+  }
+
+  fn command(config: &Config) -> Result<Command, String> {
     let home_path = find_stainless_home()?;
     let exec_path = home_path.join("stainless");
     if !exec_path.is_file() {
@@ -68,63 +123,49 @@ impl Backend {
     if let Ok(extra_flags) = env::var("STAINLESS_FLAGS") {
       cmd.args(extra_flags.split(' '));
     }
-
-    let child = cmd
-      .stdin(Stdio::piped())
-      .stdout(Stdio::piped())
-      .spawn()
-      .map_err(|err| format!("Could not spawn stainless: {}", err))?;
-    Ok(Self { config, child })
+    Ok(cmd)
   }
 
   pub fn query<P: AsRef<Path>>(&mut self, query_path: P) -> Result<Response, String> {
-    let stdin = self
-      .child
-      .stdin
-      .as_mut()
-      .expect("Stdin for stainless process is missing");
-    let mut stdin = LineWriter::new(stdin);
-    writeln!(stdin, "{}", query_path.as_ref().to_str().unwrap())
-      .map_err(|_| "Could not write query to stainless stdin")?;
-
-    let stdout = self
-      .child
-      .stdout
-      .as_mut()
-      .expect("Stdout for stainless process is missing");
-
-    // NOTE: A hacky way to debug unparsable responses from stainless:
-    // use std::io::Read;
-    // let mut buffer = String::new();
-    // stdout.read_to_string(&mut buffer).unwrap();
-    // eprintln!("Got the following from: {}", buffer);
-    // panic!();
-
-    let deserializer = serde_json::Deserializer::from_reader(stdout);
-    let mut iterator = deserializer.into_iter::<Response>();
-    iterator
-      .next()
-      .ok_or("Stainless stopped before responding")?
-      .map_err(|err| format!("Failed to parse response: {}", err))
+    let mut process = match self.process.take() {
+      Some(process) => process,
+      None => Process::spawn(Self::command(&self.config)?)?,
+    };
+    let response = process.query(query_path.as_ref(), self.config.query_timeout);
+    // After a failure, Stainless can't be trusted to answer further queries
+    match response {
+      Ok(_) => self.process = Some(process),
+      Err(_) => process.kill(),
+    }
+    response
   }
+}
 
-  pub fn query_for_program(&mut self, symbols: &st::Symbols) -> Result<Response, String> {
-    use stainless_data::ser::*;
-    use tempfile::NamedTempFile;
-
-    let mut file = NamedTempFile::new().expect("Unable to create temporary example file");
-    let mut s = BufferSerializer::new();
-    symbols
-      .serialize(&mut s)
-      .expect("Failed to serialize stainless program");
-    file
-      .write_all(s.as_slice())
-      .expect("Unable to write example file");
-
+impl VerifierBackend for Backend {
+  fn query_for_program(&mut self, symbols: &st::Symbols) -> Result<Response, String> {
+    let file = write_program(symbols);
     self.query(file.path())
   }
 }
 
+/// Write a program to a temporary file, which is deleted once dropped
+fn write_program(symbols: &st::Symbols) -> tempfile::NamedTempFile {
+  let mut file = tempfile::NamedTempFile::new().expect("Unable to create temporary example file");
+  file
+    .write_all(&serialize_program(symbols))
+    .expect("Unable to write example file");
+  file
+}
+
+fn serialize_program(symbols: &st::Symbols) -> Vec<u8> {
+  use stainless_data::ser::*;
+  let mut s = BufferSerializer::new();
+  symbols
+    .serialize(&mut s)
+    .expect("Failed to serialize stainless program");
+  s.as_slice().to_vec()
+}
+
 fn find_stainless_home() -> Result<PathBuf, String> {
   match std::env::var("STAINLESS_HOME") {
     Ok(home_path) => Ok(PathBuf::from(home_path)),
@@ -134,9 +175,30 @@ fn find_stainless_home() -> Result<PathBuf, String> {
   }
 }
 
-/// Convenience method to verify a single program
+/// Create the backend to verify programs with. This is the Stainless process, unless
+/// `STAINLESS_MOCK_RESPONSES` names a file of canned responses to replay instead.
+pub fn create_backend(config: Config) -> Result<Box<dyn VerifierBackend>, String> {
+  match env::var(MOCK_RESPONSES_VAR) {
+    Ok(path) => Ok(Box::new(MockBackend::from_file(path)?)),
+    Err(_) => Ok(Box::new(Backend::create(config)?)),
+  }
+}
+
+/// Convenience method to verify a single program, which is split up among a pool of Stainless
+/// processes if the config asks for more than one.
 pub fn verify_program(config: Config, symbols: &st::Symbols) -> Result<Report, String> {
-  let mut backend = Backend::create(config)?;
+  if config.processes > 1 && env::var(MOCK_RESPONSES_VAR).is_err() {
+    return BackendPool::create(config)?.verify_program(symbols);
+  }
+  let mut backend = create_backend(config)?;
+  verify_program_with(&mut *backend, symbols)
+}
+
+/// Verify a single program using the given backend
+pub fn verify_program_with<B: VerifierBackend + ?Sized>(
+  backend: &mut B,
+  symbols: &st::Symbols,
+) -> Result<Report, String> {
   let response = backend.query_for_program(symbols)?;
   response
     .into_verification_report()
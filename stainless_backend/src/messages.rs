@@ -1,6 +1,8 @@
 use serde::Deserialize;
 use std::fmt;
 
+use stainless_data::ast as st;
+
 #[derive(Debug, Deserialize)]
 #[serde(tag = "status")]
 pub enum Response {
@@ -47,7 +49,10 @@ pub enum VerificationStatus {
   Valid {},
   ValidFromCache {},
   Inconclusive {},
-  Invalid {},
+  Invalid {
+    #[serde(default)]
+    model: Model,
+  },
 }
 
 impl VerificationStatus {
@@ -65,20 +70,195 @@ impl fmt::Display for VerificationStatus {
       VerificationStatus::Valid {} => write!(f, "Valid"),
       VerificationStatus::ValidFromCache {} => write!(f, "Valid from cache"),
       VerificationStatus::Inconclusive {} => write!(f, "Unknown"),
-      VerificationStatus::Invalid {} => write!(f, "Invalid"),
+      VerificationStatus::Invalid { .. } => write!(f, "Invalid"),
     }
   }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, PartialEq, Eq)]
 pub struct SourceIdentifier {
   pub name: String,
   pub gid: usize,
   pub id: usize,
 }
 
+/// A counterexample to a VC, assigning values to the variables it is about, which are usually
+/// the parameters of a function.
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Model {
+  pub bindings: Vec<ModelBinding>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct ModelBinding {
+  pub id: SourceIdentifier,
+  pub value: Value,
+}
+
+/// A value in a counterexample. Values Stainless can't express in these terms, like functions,
+/// are only given in Stainless' own syntax.
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub enum Value {
+  BooleanLiteral {
+    value: bool,
+  },
+  BVLiteral {
+    signed: bool,
+    value: String,
+    size: usize,
+  },
+  IntegerLiteral {
+    value: String,
+  },
+  CharLiteral {
+    value: char,
+  },
+  StringLiteral {
+    value: String,
+  },
+  UnitLiteral {},
+  Tuple {
+    exprs: Vec<Value>,
+  },
+  ADT {
+    id: SourceIdentifier,
+    args: Vec<Value>,
+  },
+  Other {
+    repr: String,
+  },
+}
+
+impl Model {
+  pub fn is_empty(&self) -> bool {
+    self.bindings.is_empty()
+  }
+
+  /// Render the model in Rust syntax, like `x = 2147483647_i32, xs = Cons { head: 0, tail: Nil }`.
+  /// Variables are named after the local bindings they were extracted from, and ADT values after
+  /// their constructors, as found in the given symbols.
+  pub fn to_rust(&self, symbols: &st::Symbols) -> String {
+    self
+      .bindings
+      .iter()
+      .map(|binding| {
+        let name = symbols
+          .local_names
+          .get(&(binding.id.gid as i32))
+          .unwrap_or(&binding.id.name);
+        format!("{} = {}", name, binding.value.to_rust(symbols, true))
+      })
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+}
+
+impl Value {
+  /// Render the value in Rust syntax. Integer literals are suffixed with their type, unless the
+  /// context already determines it, like for the fields of ADTs.
+  pub fn to_rust(&self, symbols: &st::Symbols, suffixed: bool) -> String {
+    match self {
+      Value::BooleanLiteral { value } => value.to_string(),
+      Value::BVLiteral {
+        signed,
+        value,
+        size,
+      } if suffixed => format!("{}_{}{}", value, if *signed { "i" } else { "u" }, size),
+      Value::BVLiteral { value, .. } | Value::IntegerLiteral { value } => value.clone(),
+      Value::CharLiteral { value } => format!("{:?}", value),
+      Value::StringLiteral { value } => format!("{:?}", value),
+      Value::UnitLiteral {} => "()".into(),
+      Value::Tuple { exprs } => {
+        let exprs: Vec<_> = exprs
+          .iter()
+          .map(|expr| expr.to_rust(symbols, suffixed))
+          .collect();
+        format!("({})", exprs.join(", "))
+      }
+      Value::ADT { id, args } => {
+        let cons = symbols
+          .sorts
+          .values()
+          .flat_map(|sort| sort.constructors.iter())
+          .find(|cons| cons.id.id.globalId as usize == id.gid);
+        let field_names: Vec<&str> = match cons {
+          Some(cons) => cons
+            .fields
+            .iter()
+            .map(|field| field.v.id.id.name.as_str())
+            .collect(),
+          None => vec![],
+        };
+        let args: Vec<_> = args.iter().map(|arg| arg.to_rust(symbols, false)).collect();
+
+        // Fields of tuple structs and variants are named by their index, prefixed by `_`
+        let is_tuple_like = field_names.iter().all(|name| {
+          name.starts_with('_') && name.len() > 1 && name[1..].chars().all(char::is_numeric)
+        });
+        if args.is_empty() {
+          id.name.clone()
+        } else if field_names.len() != args.len() || is_tuple_like {
+          format!("{}({})", id.name, args.join(", "))
+        } else {
+          let fields: Vec<_> = field_names
+            .iter()
+            .zip(args)
+            .map(|(name, arg)| format!("{}: {}", name, arg))
+            .collect();
+          format!("{} {{ {} }}", id.name, fields.join(", "))
+        }
+      }
+      Value::Other { repr } => repr.clone(),
+    }
+  }
+}
+
+/// The position Stainless reports for a result, which is that of the tree the result is about,
+/// as we serialized it. Lines and columns are 1-based.
 #[derive(Debug, Deserialize)]
-pub struct SourcePosition {}
+pub struct SourcePosition {
+  pub kind: PositionKind,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub enum PositionKind {
+  Unknown {},
+  Offset {
+    file: String,
+    line: usize,
+    col: usize,
+  },
+  #[serde(rename_all = "camelCase")]
+  Range {
+    file: String,
+    line_from: usize,
+    col_from: usize,
+    line_to: usize,
+    col_to: usize,
+  },
+}
+
+impl SourcePosition {
+  pub fn is_known(&self) -> bool {
+    self.kind != PositionKind::Unknown {}
+  }
+}
+
+impl fmt::Display for SourcePosition {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.kind {
+      PositionKind::Unknown {} => write!(f, "?"),
+      PositionKind::Offset { file, line, col } => write!(f, "{}:{}:{}", file, line, col),
+      PositionKind::Range {
+        file,
+        line_from,
+        col_from,
+        ..
+      } => write!(f, "{}:{}:{}", file, line_from, col_from),
+    }
+  }
+}
 
 #[test]
 fn test_parse_report() {
@@ -137,6 +317,84 @@ fn test_parse_report() {
   let _msg: Response = serde_json::from_str(msg).unwrap();
 }
 
+#[test]
+fn test_parse_position() {
+  let msg = r#"{
+    "kind" : {
+      "Range" : {
+        "file" : "src/lib.rs",
+        "lineFrom" : 3,
+        "colFrom" : 5,
+        "pointFrom" : 40,
+        "lineTo" : 3,
+        "colTo" : 10,
+        "pointTo" : 45
+      }
+    }
+  }"#;
+  let pos: SourcePosition = serde_json::from_str(msg).unwrap();
+  assert_eq!(
+    pos.kind,
+    PositionKind::Range {
+      file: "src/lib.rs".into(),
+      line_from: 3,
+      col_from: 5,
+      line_to: 3,
+      col_to: 10,
+    }
+  );
+  assert_eq!(pos.to_string(), "src/lib.rs:3:5");
+}
+
+#[test]
+fn test_parse_model() {
+  let msg = r#"{
+    "Invalid" : {
+      "model" : [
+        {
+          "id" : { "name" : "x", "gid" : 3, "id" : 1 },
+          "value" : {
+            "BVLiteral" : { "signed" : true, "value" : "2147483647", "size" : 32 }
+          }
+        },
+        {
+          "id" : { "name" : "xs", "gid" : 4, "id" : 2 },
+          "value" : {
+            "ADT" : {
+              "id" : { "name" : "Cons", "gid" : 8, "id" : 0 },
+              "args" : [
+                { "IntegerLiteral" : { "value" : "0" } },
+                { "ADT" : { "id" : { "name" : "Nil", "gid" : 9, "id" : 0 }, "args" : [] } }
+              ]
+            }
+          }
+        }
+      ]
+    }
+  }"#;
+  let status: VerificationStatus = serde_json::from_str(msg).unwrap();
+  let model = match status {
+    VerificationStatus::Invalid { model } => model,
+    _ => panic!("Expected an invalid status"),
+  };
+
+  let f = st::Factory::new();
+  let id = |name: &str, gid| f.SymbolIdentifier(f.Identifier(name.into(), gid, 0), vec![]);
+  let list_id = id("List", 7);
+  let int_tpe = f.IntegerType().into();
+  let list_tpe = f.ADTType(list_id, vec![]).into();
+  let field = |name: &str, gid, tpe| &*f.ValDef(f.Variable(id(name, gid), tpe, vec![]));
+  let fields = vec![field("head", 10, int_tpe), field("tail", 11, list_tpe)];
+  let cons = f.ADTConstructor(id("Cons", 8), list_id, fields);
+  let nil = f.ADTConstructor(id("Nil", 9), list_id, vec![]);
+  let list = f.ADTSort(list_id, vec![], vec![cons, nil], vec![]);
+  let symbols = st::Symbols::new(vec![list], vec![], vec![]);
+  assert_eq!(
+    model.to_rust(&symbols),
+    "x = 2147483647_i32, xs = Cons { head: 0, tail: Nil }"
+  );
+}
+
 #[test]
 fn test_parse_error() {
   let msg = r#"{
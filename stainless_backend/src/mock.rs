@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+use std::path::Path;
+
+use stainless_data::ast as st;
+
+use super::messages::Response;
+use super::{serialize_program, VerifierBackend};
+
+/// A backend replaying canned responses in order, one per query, which allows testing
+/// everything around verification without a Stainless installation.
+pub struct MockBackend {
+  responses: VecDeque<String>,
+  num_queries: usize,
+}
+
+impl MockBackend {
+  pub fn new<I: IntoIterator<Item = String>>(responses: I) -> Self {
+    Self {
+      responses: responses.into_iter().collect(),
+      num_queries: 0,
+    }
+  }
+
+  /// Read the responses from a file containing a sequence of JSON responses, as Stainless
+  /// would print them.
+  pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+      .map_err(|err| format!("Could not read {}: {}", path.to_string_lossy(), err))?;
+    let responses = serde_json::Deserializer::from_str(&contents)
+      .into_iter::<serde_json::Value>()
+      .map(|value| value.map(|value| value.to_string()))
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|err| format!("Failed to parse canned responses: {}", err))?;
+    Ok(Self::new(responses))
+  }
+
+  /// The number of queries answered so far
+  pub fn num_queries(&self) -> usize {
+    self.num_queries
+  }
+}
+
+impl VerifierBackend for MockBackend {
+  fn query_for_program(&mut self, symbols: &st::Symbols) -> Result<Response, String> {
+    // Programs are still serialized, as they would be for Stainless
+    serialize_program(symbols);
+
+    let response = self
+      .responses
+      .pop_front()
+      .ok_or("Mock backend ran out of responses")?;
+    self.num_queries += 1;
+    serde_json::from_str(&response).map_err(|err| format!("Failed to parse response: {}", err))
+  }
+}
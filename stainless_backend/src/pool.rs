@@ -0,0 +1,351 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use stainless_data::ast as st;
+use stainless_data::ser::SymbolCollector;
+
+use super::messages::{Report, Response};
+use super::{write_program, Backend, Config};
+
+/// A pool of Stainless processes. Programs are split into units that can be verified
+/// independently, which are then dispatched to whichever process is idle.
+pub struct BackendPool {
+  backends: Vec<Backend>,
+}
+
+impl BackendPool {
+  pub fn create(config: Config) -> Result<Self, String> {
+    let backends = (0..config.processes)
+      .map(|_| Backend::create(config.clone()))
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Self { backends })
+  }
+
+  pub fn verify_program(&mut self, symbols: &st::Symbols) -> Result<Report, String> {
+    let factory = st::Factory::new();
+    let units = split_program(&factory, symbols);
+    let files: Vec<_> = units
+      .iter()
+      .map(|unit| write_program(&unit.symbols))
+      .collect();
+    let paths: Arc<Vec<PathBuf>> = Arc::new(files.iter().map(|file| file.path().into()).collect());
+
+    // Every process answers queries for units until there are none left
+    let jobs = Arc::new(Mutex::new((0..units.len()).collect::<VecDeque<_>>()));
+    let (sender, receiver) = mpsc::channel();
+    let workers: Vec<_> = self
+      .backends
+      .drain(..)
+      .map(|mut backend| {
+        let (jobs, paths, sender) = (jobs.clone(), paths.clone(), sender.clone());
+        thread::spawn(move || {
+          loop {
+            let job = match jobs.lock().unwrap().pop_front() {
+              Some(job) => job,
+              None => break,
+            };
+            if sender.send((job, backend.query(&paths[job]))).is_err() {
+              break;
+            }
+          }
+          backend
+        })
+      })
+      .collect();
+    drop(sender);
+
+    let mut responses: Vec<Option<Result<Response, String>>> = units.iter().map(|_| None).collect();
+    for (job, response) in receiver {
+      responses[job] = Some(response);
+    }
+    self.backends = workers
+      .into_iter()
+      .map(|worker| worker.join().expect("Verification worker panicked"))
+      .collect();
+
+    let reports = units
+      .iter()
+      .zip(responses)
+      .map(|(unit, response)| {
+        response
+          .unwrap_or_else(|| Err("No process answered".into()))
+          .and_then(|response| {
+            response
+              .into_verification_report()
+              .ok_or_else(|| "No verification report found".into())
+          })
+          .map(|report| (unit.root, report))
+          .map_err(|msg| format!("Failed to verify {}: {}", unit.root, msg))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(merge_reports(reports))
+  }
+}
+
+/// A part of a program that can be verified on its own, consisting of a function and all the
+/// definitions it depends on.
+pub struct Unit<'a> {
+  pub root: &'a st::SymbolIdentifier<'a>,
+  pub symbols: st::Symbols<'a>,
+}
+
+#[derive(Clone, Copy)]
+enum Definition<'a> {
+  Function(&'a st::FunDef<'a>),
+  Sort(&'a st::ADTSort<'a>),
+  Class(&'a st::ClassDef<'a>),
+}
+
+/// Split a program into one unit per function Stainless has to verify. Units contain the
+/// definitions their function refers to, transitively, as well as all the methods and subclasses
+/// of the classes they contain, which calls may be dispatched to. The functions other than the
+/// root are flagged as library functions, so that Stainless only verifies them in their own unit.
+pub fn split_program<'a>(f: &'a st::Factory, symbols: &st::Symbols<'a>) -> Vec<Unit<'a>> {
+  let mut definitions: HashMap<i32, Definition<'a>> = HashMap::new();
+  let mut constructor_sorts: HashMap<i32, i32> = HashMap::new();
+  for (id, &fd) in &symbols.functions {
+    definitions.insert(id.id.globalId, Definition::Function(fd));
+  }
+  for (id, &sort) in &symbols.sorts {
+    definitions.insert(id.id.globalId, Definition::Sort(sort));
+    for cons in &sort.constructors {
+      constructor_sorts.insert(cons.id.id.globalId, id.id.globalId);
+    }
+  }
+  for (id, &cd) in &symbols.classes {
+    definitions.insert(id.id.globalId, Definition::Class(cd));
+  }
+
+  // The definitions each definition refers to, and those its classes dispatch to
+  let mut dependencies: HashMap<i32, Vec<i32>> = HashMap::new();
+  for (&gid, &definition) in &definitions {
+    let referenced = match definition {
+      Definition::Function(fd) => SymbolCollector::collect(fd),
+      Definition::Sort(sort) => SymbolCollector::collect(sort),
+      Definition::Class(cd) => SymbolCollector::collect(cd),
+    };
+    let deps = referenced
+      .into_iter()
+      .map(|id| constructor_sorts.get(&id).copied().unwrap_or(id))
+      .filter(|id| *id != gid && definitions.contains_key(id))
+      .collect();
+    dependencies.entry(gid).or_default().extend::<Vec<_>>(deps);
+
+    let dispatched_from: Vec<i32> = match definition {
+      Definition::Function(fd) => fd
+        .flags
+        .iter()
+        .filter_map(|flag| match flag {
+          st::Flag::IsMethodOf(st::IsMethodOf { id }) => Some(id.id.globalId),
+          _ => None,
+        })
+        .collect(),
+      Definition::Class(cd) => cd.parents.iter().map(|ct| ct.id.id.globalId).collect(),
+      Definition::Sort(_) => vec![],
+    };
+    for class_gid in dispatched_from {
+      dependencies.entry(class_gid).or_default().push(gid);
+    }
+  }
+
+  let is_verified = |fd: &st::FunDef| {
+    !fd.flags.iter().any(|flag| {
+      matches!(
+        flag,
+        st::Flag::Library(_) | st::Flag::Extern(_) | st::Flag::IsAbstract(_)
+      )
+    })
+  };
+  let mut roots: Vec<&st::FunDef<'a>> = symbols
+    .functions
+    .values()
+    .copied()
+    .filter(|fd| is_verified(fd))
+    .collect();
+  roots.sort();
+
+  roots
+    .into_iter()
+    .map(|root| {
+      let mut reached: HashSet<i32> = HashSet::new();
+      let mut worklist = vec![root.id.id.globalId];
+      while let Some(gid) = worklist.pop() {
+        if reached.insert(gid) {
+          worklist.extend(dependencies.get(&gid).into_iter().flatten());
+        }
+      }
+
+      let (mut sorts, mut functions, mut classes) = (vec![], vec![], vec![]);
+      for gid in reached {
+        match definitions[&gid] {
+          Definition::Function(fd) if fd.id != root.id && is_verified(fd) => {
+            let mut flags = fd.flags.clone();
+            flags.push(f.Library().into());
+            functions.push(&*f.FunDef(
+              fd.id,
+              fd.tparams.clone(),
+              fd.params.clone(),
+              fd.returnType,
+              fd.fullBody,
+              flags,
+            ));
+          }
+          Definition::Function(fd) => functions.push(fd),
+          Definition::Sort(sort) => sorts.push(sort),
+          Definition::Class(cd) => classes.push(cd),
+        }
+      }
+      let symbols =
+        st::Symbols::new(sorts, functions, classes).with_overrides(symbols.overrides.clone());
+      Unit {
+        root: root.id,
+        symbols,
+      }
+    })
+    .collect()
+}
+
+/// Merge the reports for the units of a program, given along with their roots. Units only verify
+/// their root, so only the results for it, and for nothing else, are kept from each report.
+pub fn merge_reports(reports: Vec<(&st::SymbolIdentifier, Report)>) -> Report {
+  let mut all_results = vec![];
+  let mut all_sources = vec![];
+  for (root, report) in reports {
+    let is_root = |gid: usize| gid as i32 == root.id.globalId;
+    match report {
+      Report::Verification { results, sources } => {
+        all_results.extend(
+          results
+            .into_iter()
+            .filter(|result| is_root(result.id.gid) || is_root(result.derived_from.gid)),
+        );
+        all_sources.extend(sources.into_iter().filter(|source| is_root(source.gid)));
+      }
+    }
+  }
+  Report::Verification {
+    results: all_results,
+    sources: all_sources,
+  }
+}
+
+#[test]
+fn test_split_program() {
+  let f = st::Factory::new();
+  let id = |name: &str, gid| f.SymbolIdentifier(f.Identifier(name.into(), gid, 0), vec![]);
+  let int_tpe: st::Type = f.Int32Type().into();
+  let zero: st::Expr = f.Int32Literal(0).into();
+
+  // A sort used by `c`, which calls `b`, and `a`, which calls the library function `l`
+  let sort_id = id("S", 1);
+  let cons = f.ADTConstructor(id("S", 2), sort_id, vec![]);
+  let sort = f.ADTSort(sort_id, vec![], vec![cons], vec![]);
+  let fun = |fun_id, body, flags| &*f.FunDef(fun_id, vec![], vec![], int_tpe, body, flags);
+  let l = fun(id("l", 3), zero, vec![f.Library().into()]);
+  let a = fun(
+    id("a", 4),
+    f.FunctionInvocation(l.id, vec![], vec![]).into(),
+    vec![],
+  );
+  let b = fun(id("b", 5), zero, vec![]);
+  let c_body = f.Let(
+    f.ValDef(f.Variable(id("s", 7), f.ADTType(sort_id, vec![]).into(), vec![])),
+    f.ADT(cons.id, vec![], vec![]).into(),
+    f.FunctionInvocation(b.id, vec![], vec![]).into(),
+  );
+  let c = fun(id("c", 6), c_body.into(), vec![]);
+  let symbols = st::Symbols::new(vec![sort], vec![l, a, b, c], vec![]);
+
+  let units = split_program(&f, &symbols);
+  let unit_gids: Vec<(String, Vec<i32>)> = units
+    .iter()
+    .map(|unit| {
+      let mut gids: Vec<i32> = (unit.symbols.sorts.keys())
+        .chain(unit.symbols.functions.keys())
+        .map(|id| id.id.globalId)
+        .collect();
+      gids.sort();
+      (unit.root.id.name.clone(), gids)
+    })
+    .collect();
+  assert_eq!(
+    unit_gids,
+    vec![
+      ("a".into(), vec![3, 4]),
+      ("b".into(), vec![5]),
+      ("c".into(), vec![1, 5, 6]),
+    ]
+  );
+
+  // `b` is only verified in its own unit
+  let is_library = |unit: &Unit, fun_id| {
+    unit.symbols.functions[fun_id]
+      .flags
+      .iter()
+      .any(|flag| matches!(flag, st::Flag::Library(_)))
+  };
+  assert!(!is_library(&units[1], b.id));
+  assert!(is_library(&units[2], b.id));
+  assert!(!is_library(&units[2], c.id));
+}
+
+#[test]
+fn test_merge_reports() {
+  let f = st::Factory::new();
+  let id = |name: &str, gid| f.SymbolIdentifier(f.Identifier(name.into(), gid, 0), vec![]);
+  let report = |results: &[(&str, usize, &str)], sources: &[(&str, usize)]| -> Report {
+    let results: Vec<String> = results
+      .iter()
+      .map(|(name, gid, status)| {
+        format!(
+          r#"{{
+            "id" : {{ "name" : "{0}", "gid" : {1}, "id" : 0 }},
+            "pos" : {{ "kind" : {{ "Unknown" : {{}} }} }},
+            "time" : 0,
+            "status" : {{ "{2}" : {{}} }},
+            "solverName" : null,
+            "kind" : "body assertion",
+            "derivedFrom" : {{ "name" : "{0}", "gid" : {1}, "id" : 0 }}
+          }}"#,
+          name, gid, status
+        )
+      })
+      .collect();
+    let sources: Vec<String> = sources
+      .iter()
+      .map(|(name, gid)| format!(r#"{{ "name" : "{}", "gid" : {}, "id" : 0 }}"#, name, gid))
+      .collect();
+    let msg = format!(
+      r#"{{ "component" : "Verification", "data" : {{ "results" : [{}], "sources" : [{}] }} }}"#,
+      results.join(", "),
+      sources.join(", ")
+    );
+    serde_json::from_str(&msg).unwrap()
+  };
+
+  // `a` has two assertions of the same kind and without positions, one of which fails, and `b`
+  // shows up in the report of `a`'s unit even though it's only verified in its own unit
+  let merged = merge_reports(vec![
+    (
+      id("a", 1),
+      report(
+        &[("a", 1, "Valid"), ("a", 1, "Invalid"), ("b", 2, "Valid")],
+        &[("a", 1), ("b", 2)],
+      ),
+    ),
+    (id("b", 2), report(&[("b", 2, "Inconclusive")], &[("b", 2)])),
+  ]);
+  match merged {
+    Report::Verification { results, sources } => {
+      let names: Vec<&str> = results.iter().map(|r| r.id.name.as_str()).collect();
+      assert_eq!(names, vec!["a", "a", "b"]);
+      let valid: Vec<bool> = results.iter().map(|r| r.status.is_valid()).collect();
+      assert_eq!(valid, vec![true, false, false]);
+      let sources: Vec<&str> = sources.iter().map(|s| s.name.as_str()).collect();
+      assert_eq!(sources, vec!["a", "b"]);
+    }
+  }
+}
@@ -0,0 +1,219 @@
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::messages::Response;
+
+/// How long Stainless gets to exit on its own once we close its stdin
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How much of Stainless' stderr we keep around to report errors
+const MAX_STDERR_LEN: usize = 64 * 1024;
+
+/// A running Stainless process. Its stdout and stderr are read by background threads, such that
+/// we never block on a process that hangs, and it doesn't block on us not reading its stderr.
+pub(crate) struct Process {
+  child: Child,
+  responses: Receiver<Result<Response, String>>,
+  stderr: Arc<Mutex<String>>,
+  stderr_closed: Receiver<()>,
+}
+
+impl Process {
+  pub(crate) fn spawn(mut cmd: Command) -> Result<Self, String> {
+    let mut child = cmd
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|err| format!("Could not spawn stainless: {}", err))?;
+
+    let stdout = child
+      .stdout
+      .take()
+      .expect("Stdout for stainless process is missing");
+    let (sender, responses) = mpsc::channel();
+    thread::spawn(move || {
+      let deserializer = serde_json::Deserializer::from_reader(BufReader::new(stdout));
+      for response in deserializer.into_iter::<Response>() {
+        let response = response.map_err(|err| format!("Failed to parse response: {}", err));
+        let failed = response.is_err();
+        if sender.send(response).is_err() || failed {
+          break;
+        }
+      }
+    });
+
+    let stderr = Arc::new(Mutex::new(String::new()));
+    let child_stderr = child
+      .stderr
+      .take()
+      .expect("Stderr for stainless process is missing");
+    let stderr_buffer = stderr.clone();
+    let (stderr_sender, stderr_closed) = mpsc::channel();
+    thread::spawn(move || {
+      for line in BufReader::new(child_stderr).lines() {
+        let line = match line {
+          Ok(line) => line,
+          Err(_) => break,
+        };
+        let mut buffer = stderr_buffer.lock().unwrap();
+        buffer.push_str(&line);
+        buffer.push('\n');
+        if buffer.len() > MAX_STDERR_LEN {
+          let mut cut = buffer.len() - MAX_STDERR_LEN;
+          while !buffer.is_char_boundary(cut) {
+            cut += 1;
+          }
+          buffer.drain(..cut);
+        }
+      }
+      let _ = stderr_sender.send(());
+    });
+
+    Ok(Self {
+      child,
+      responses,
+      stderr,
+      stderr_closed,
+    })
+  }
+
+  pub(crate) fn query(
+    &mut self,
+    query_path: &Path,
+    timeout: Option<Duration>,
+  ) -> Result<Response, String> {
+    self.stderr.lock().unwrap().clear();
+
+    let written = {
+      let stdin = self
+        .child
+        .stdin
+        .as_mut()
+        .expect("Stdin for stainless process is missing");
+      writeln!(LineWriter::new(stdin), "{}", query_path.to_str().unwrap())
+    };
+    if written.is_err() {
+      return Err(self.error("Could not write query to stainless stdin"));
+    }
+
+    let response = match timeout {
+      Some(timeout) => self.responses.recv_timeout(timeout),
+      None => self
+        .responses
+        .recv()
+        .map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    match response {
+      Ok(Ok(response)) => Ok(response),
+      Ok(Err(msg)) => Err(self.error(&msg)),
+      Err(RecvTimeoutError::Timeout) => Err(self.error(&format!(
+        "Stainless did not respond within {:?}",
+        timeout.unwrap()
+      ))),
+      Err(RecvTimeoutError::Disconnected) => {
+        // Give the process a moment to exit and to finish writing to stderr
+        let _ = self.stderr_closed.recv_timeout(Duration::from_secs(1));
+        let msg = match self.child.try_wait() {
+          Ok(Some(status)) => format!("Stainless stopped before responding ({})", status),
+          _ => "Stainless stopped before responding".into(),
+        };
+        Err(self.error(&msg))
+      }
+    }
+  }
+
+  /// An error message including what Stainless printed to stderr during the current query
+  fn error(&self, msg: &str) -> String {
+    let stderr = self.stderr.lock().unwrap();
+    if stderr.trim().is_empty() {
+      msg.into()
+    } else {
+      format!("{}. Stainless reported:\n{}", msg, stderr.trim_end())
+    }
+  }
+
+  /// Kill the process right away, for when it crashed or hangs
+  pub(crate) fn kill(mut self) {
+    self.terminate(Duration::from_secs(0));
+  }
+
+  /// Ask the process to exit by closing its stdin, and kill it if it doesn't within the given
+  /// grace period.
+  fn terminate(&mut self, grace_period: Duration) {
+    drop(self.child.stdin.take());
+    let deadline = Instant::now() + grace_period;
+    while Instant::now() < deadline {
+      match self.child.try_wait() {
+        Ok(None) => thread::sleep(Duration::from_millis(50)),
+        _ => return,
+      }
+    }
+    let _ = self.child.kill();
+    let _ = self.child.wait();
+  }
+}
+
+impl Drop for Process {
+  fn drop(&mut self) {
+    self.terminate(SHUTDOWN_GRACE_PERIOD);
+  }
+}
+
+#[cfg(all(test, unix))]
+fn shell(script: &str) -> Process {
+  let mut cmd = Command::new("sh");
+  cmd.arg("-c").arg(script);
+  Process::spawn(cmd).unwrap()
+}
+
+#[cfg(all(test, unix))]
+#[test]
+fn test_query_response() {
+  let mut process = shell(r#"read query; echo '{"status": "Error", "msg": "'$query'"}'"#);
+  let timeout = Some(Duration::from_secs(10));
+  match process.query(Path::new("program.inoxser"), timeout) {
+    Ok(Response::Error { msg }) => assert_eq!(msg, "program.inoxser"),
+    response => panic!("Unexpected response: {:?}", response),
+  }
+}
+
+#[cfg(all(test, unix))]
+#[test]
+fn test_query_timeout() {
+  let mut process = shell("sleep 30");
+  let start = Instant::now();
+  let err = process
+    .query(
+      Path::new("program.inoxser"),
+      Some(Duration::from_millis(100)),
+    )
+    .unwrap_err();
+  assert!(
+    err.starts_with("Stainless did not respond within 100ms"),
+    "{}",
+    err
+  );
+  process.kill();
+  assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[cfg(all(test, unix))]
+#[test]
+fn test_query_crash() {
+  let mut process = shell("read query; echo 'Out of memory' >&2; exit 3");
+  let err = process
+    .query(Path::new("program.inoxser"), Some(Duration::from_secs(10)))
+    .unwrap_err();
+  assert!(
+    err.starts_with("Stainless stopped before responding"),
+    "{}",
+    err
+  );
+  assert!(err.ends_with("Out of memory"), "{}", err);
+}
@@ -0,0 +1,83 @@
+extern crate stainless_backend;
+
+use stainless_data::ast::Factory;
+
+use stainless_backend::messages::{Report, VerificationStatus};
+use stainless_backend::mock::MockBackend;
+use stainless_backend::{verify_program_with, VerifierBackend};
+
+pub mod examples;
+
+fn response(status: &str) -> String {
+  format!(
+    r#"{{
+      "status" : "Success",
+      "reports" : [
+        {{
+          "component" : "Verification",
+          "data" : {{
+            "results" : [
+              {{
+                "id" : {{ "name" : "id", "gid" : 1, "id" : 0 }},
+                "pos" : {{ "kind" : {{ "Unknown" : {{}} }} }},
+                "time" : 0,
+                "status" : {},
+                "solverName" : null,
+                "kind" : "postcondition",
+                "derivedFrom" : {{ "name" : "id", "gid" : 1, "id" : 0 }}
+              }}
+            ],
+            "sources" : [{{ "name" : "id", "gid" : 1, "id" : 0 }}]
+          }}
+        }}
+      ]
+    }}"#,
+    status
+  )
+}
+
+fn statuses(report: Report) -> Vec<VerificationStatus> {
+  match report {
+    Report::Verification { results, .. } => results.into_iter().map(|r| r.status).collect(),
+  }
+}
+
+#[test]
+fn test_replays_responses() {
+  let f = Factory::new();
+  let symbols = examples::identity_symbols(&f);
+  let mut backend = MockBackend::new(vec![
+    response(r#"{ "Valid" : {} }"#),
+    response(r#"{ "Invalid" : {} }"#),
+  ]);
+
+  let report = verify_program_with(&mut backend, &symbols).unwrap();
+  assert_eq!(statuses(report), vec![VerificationStatus::Valid {}]);
+  let report = verify_program_with(&mut backend, &symbols).unwrap();
+  assert!(!statuses(report)[0].is_valid());
+  assert_eq!(backend.num_queries(), 2);
+
+  assert!(backend.query_for_program(&symbols).is_err());
+}
+
+#[test]
+fn test_responses_from_file() {
+  use std::io::Write;
+
+  let mut file = tempfile::NamedTempFile::new().unwrap();
+  write!(
+    file,
+    "{}\n{}",
+    response(r#"{ "ValidFromCache" : {} }"#),
+    response(r#"{ "Inconclusive" : {} }"#)
+  )
+  .unwrap();
+  let mut backend = MockBackend::from_file(file.path()).unwrap();
+
+  let f = Factory::new();
+  let symbols = examples::identity_symbols(&f);
+  let report = verify_program_with(&mut backend, &symbols).unwrap();
+  assert!(statuses(report)[0].is_valid());
+  let report = verify_program_with(&mut backend, &symbols).unwrap();
+  assert_eq!(statuses(report), vec![VerificationStatus::Inconclusive {}]);
+}
@@ -0,0 +1,52 @@
+#![cfg(unix)]
+extern crate stainless_backend;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use stainless_data::ast::Factory;
+
+use stainless_backend::messages::Report;
+use stainless_backend::pool::BackendPool;
+use stainless_backend::Config;
+
+pub mod examples;
+
+/// Answers every query with a valid postcondition for the identity function
+const FAKE_STAINLESS: &str = r#"#!/bin/sh
+while read query; do
+  echo '{ "status" : "Success", "reports" : [ { "component" : "Verification", "data" : {
+    "results" : [ {
+      "id" : { "name" : "f", "gid" : 2, "id" : 0 },
+      "pos" : { "kind" : { "Unknown" : {} } },
+      "time" : 0,
+      "status" : { "Valid" : {} },
+      "solverName" : null,
+      "kind" : "postcondition",
+      "derivedFrom" : { "name" : "f", "gid" : 2, "id" : 0 }
+    } ],
+    "sources" : [ { "name" : "f", "gid" : 2, "id" : 0 } ] } } ] }'
+done
+"#;
+
+#[test]
+fn test_pool_with_fake_stainless() {
+  let home = tempfile::tempdir().unwrap();
+  let exec_path = home.path().join("stainless");
+  fs::write(&exec_path, FAKE_STAINLESS).unwrap();
+  fs::set_permissions(&exec_path, fs::Permissions::from_mode(0o755)).unwrap();
+  std::env::set_var("STAINLESS_HOME", home.path());
+
+  let f = Factory::new();
+  let symbols = examples::identity_symbols(&f);
+  let mut pool = BackendPool::create(Config::default().with_processes(2)).unwrap();
+  for _ in 0..3 {
+    match pool.verify_program(&symbols).unwrap() {
+      Report::Verification { results, sources } => {
+        assert_eq!(results.len(), 1);
+        assert!(results[0].status.is_valid());
+        assert_eq!(sources.len(), 1);
+      }
+    }
+  }
+}
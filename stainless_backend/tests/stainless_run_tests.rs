@@ -3,7 +3,7 @@ extern crate stainless_backend;
 use stainless_data::ast::Factory;
 
 use stainless_backend::messages::Report;
-use stainless_backend::{verify_program, Backend, Config};
+use stainless_backend::{verify_program, Backend, Config, VerifierBackend};
 
 pub mod examples;
 
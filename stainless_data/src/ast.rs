@@ -11,7 +11,7 @@ pub mod pretty;
 use std::hash::{Hash, Hasher};
 
 use crate::ser::types::*;
-use crate::ser::{BufferSerializer, MarkerId, Serializable, SerializationResult, Serializer};
+use crate::ser::{MarkerId, Serializable, SerializationResult, Serializer, SymbolsSerializer};
 
 use bumpalo::Bump;
 
@@ -36,21 +36,60 @@ impl Factory {
 pub struct Symbols<'a> {
   pub sorts: Map<&'a SymbolIdentifier<'a>, &'a ADTSort<'a>>,
   pub functions: Map<&'a SymbolIdentifier<'a>, &'a FunDef<'a>>,
+  pub classes: Map<&'a SymbolIdentifier<'a>, &'a ClassDef<'a>>,
+  /// The source positions of definitions, by their global ids. Inox' serialization format has no
+  /// room for positions, so they never reach Stainless, but they let us locate its results.
+  pub positions: Map<Int, Position>,
+  /// The names of local bindings in the source, by the global id of their variables
+  pub local_names: Map<Int, String>,
+  /// The symbols of overriding methods, which are those of the methods they override, by the
+  /// global ids of the overriding methods
+  pub overrides: Map<Int, Int>,
 }
 
 impl<'a> Symbols<'a> {
-  pub fn new(sorts: Seq<&'a ADTSort<'a>>, functions: Seq<&'a FunDef<'a>>) -> Self {
+  pub fn new(
+    sorts: Seq<&'a ADTSort<'a>>,
+    functions: Seq<&'a FunDef<'a>>,
+    classes: Seq<&'a ClassDef<'a>>,
+  ) -> Self {
     let mut sorts_map = Map::new();
     let mut functions_map = Map::new();
+    let mut classes_map = Map::new();
     sorts.iter().for_each(|&sort| {
       sorts_map.insert(sort.id, sort);
     });
     functions.iter().for_each(|&fd| {
       functions_map.insert(fd.id, fd);
     });
+    classes.iter().for_each(|&cd| {
+      classes_map.insert(cd.id, cd);
+    });
     Symbols {
       sorts: sorts_map,
       functions: functions_map,
+      classes: classes_map,
+      positions: Map::new(),
+      local_names: Map::new(),
+      overrides: Map::new(),
+    }
+  }
+
+  /// Attach the source positions of the definitions in these symbols
+  pub fn with_positions(self, positions: Map<Int, Position>) -> Self {
+    Self { positions, ..self }
+  }
+
+  /// Attach the symbols of the overriding methods in these symbols
+  pub fn with_overrides(self, overrides: Map<Int, Int>) -> Self {
+    Self { overrides, ..self }
+  }
+
+  /// Attach the source names of the local bindings in these symbols
+  pub fn with_local_names(self, local_names: Map<Int, String>) -> Self {
+    Self {
+      local_names,
+      ..self
     }
   }
 }
@@ -63,6 +102,9 @@ impl<'a> Hash for Symbols<'a> {
     let mut functions: Vec<_> = self.functions.values().collect();
     functions.sort();
     functions.iter().for_each(|function| function.hash(state));
+    let mut classes: Vec<_> = self.classes.values().collect();
+    classes.sort();
+    classes.iter().for_each(|class| class.hash(state));
   }
 }
 
@@ -72,14 +114,28 @@ impl<'a> Serializable for Symbols<'a> {
     sorts.sort();
     let mut functions: Vec<_> = self.functions.values().collect();
     functions.sort();
+    let mut classes: Vec<_> = self.classes.values().collect();
+    classes.sort();
 
-    let mut inner_s = BufferSerializer::new();
-    (functions, sorts).serialize(&mut inner_s)?;
+    let mut inner_s = SymbolsSerializer::new(&self.overrides);
+    (functions, sorts, classes).serialize(&mut inner_s)?;
     inner_s.to_buffer().serialize(s)?;
     Ok(())
   }
 }
 
+// Source positions
+
+/// A range of source code, with 1-based lines and columns.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+  pub file: String,
+  pub line_from: Int,
+  pub col_from: Int,
+  pub line_to: Int,
+  pub col_to: Int,
+}
+
 // Various trait implementations that are significantly different from the rest
 
 impl<'a> Serializable for ValDef<'a> {
@@ -118,13 +174,15 @@ impl Serializable for Identifier {
 impl<'a> Serializable for SymbolIdentifier<'a> {
   fn serialize<S: Serializer>(&self, s: &mut S) -> SerializationResult {
     s.write_marker(MarkerId(145))?;
-    // NOTE: We deviate from Stainless here in that we reuse the Identifier's globalId
-    // as the Symbol's id.
+    s.record_symbol(self);
+    // NOTE: We deviate from Stainless here in that we usually reuse the Identifier's
+    // globalId as the Symbol's id. Only overriding methods share their symbol with the
+    // method they override.
     (
       self.id.globalId,
       self.id.id,
       &self.symbol_path,
-      self.id.globalId,
+      s.symbol_id(self),
     )
       .serialize(s)?;
     Ok(())
@@ -104,7 +104,11 @@ test_gen!(identity_symbols, s => {
   functions.insert(fd.id, fd);
   let symbols = Symbols {
     sorts: Map::new(),
-    functions
+    functions,
+    classes: Map::new(),
+    positions: Map::new(),
+    local_names: Map::new(),
+    overrides: Map::new(),
   };
   ser!(symbols, s)
 });
@@ -4,6 +4,8 @@ extern crate num_bigint;
 
 use std::io::{self, Write};
 
+use crate::ast::SymbolIdentifier;
+
 mod serializable;
 
 pub use serializable::Serializable;
@@ -84,6 +86,14 @@ pub trait Serializer: Sized {
 
   fn writer(&mut self) -> &mut Self::Writer;
 
+  /// Called for every symbol written, which lets serializers find the symbols a tree refers to
+  fn record_symbol(&mut self, _id: &SymbolIdentifier) {}
+
+  /// The id of the symbol a symbol identifier refers to
+  fn symbol_id(&self, id: &SymbolIdentifier) -> types::Int {
+    id.id.globalId
+  }
+
   // Raw writing
 
   fn write(&mut self, data: &[u8]) -> SerializationResult {
@@ -157,6 +167,75 @@ impl Serializer for BufferSerializer {
   }
 }
 
+// SymbolsSerializer, a serializer writing to a vector, which lets overriding methods share their
+// symbol with the methods they override
+pub struct SymbolsSerializer<'o> {
+  buffer: Vec<u8>,
+  overrides: &'o types::Map<types::Int, types::Int>,
+}
+
+impl<'o> SymbolsSerializer<'o> {
+  pub fn new(overrides: &'o types::Map<types::Int, types::Int>) -> Self {
+    Self {
+      buffer: vec![],
+      overrides,
+    }
+  }
+
+  pub fn to_buffer(&self) -> SerializationBuffer {
+    SerializationBuffer(self.buffer.to_vec())
+  }
+}
+
+impl<'o> Serializer for SymbolsSerializer<'o> {
+  type Writer = Vec<u8>;
+
+  fn writer(&mut self) -> &mut Self::Writer {
+    &mut self.buffer
+  }
+
+  fn symbol_id(&self, id: &SymbolIdentifier) -> types::Int {
+    let gid = id.id.globalId;
+    self.overrides.get(&gid).copied().unwrap_or(gid)
+  }
+}
+
+// SymbolCollector, a serializer gathering the symbols trees refer to, and discarding the rest
+pub struct SymbolCollector {
+  sink: io::Sink,
+  symbols: types::Set<types::Int>,
+}
+
+impl SymbolCollector {
+  pub fn new() -> Self {
+    Self {
+      sink: io::sink(),
+      symbols: types::Set::new(),
+    }
+  }
+
+  /// The global ids of the symbols referred to by the given tree, including its own
+  pub fn collect<T: Serializable>(tree: &T) -> types::Set<types::Int> {
+    let mut s = Self::new();
+    tree
+      .serialize(&mut s)
+      .expect("Writing to a sink never fails");
+    s.symbols
+  }
+}
+
+impl Serializer for SymbolCollector {
+  type Writer = io::Sink;
+
+  fn writer(&mut self) -> &mut Self::Writer {
+    &mut self.sink
+  }
+
+  fn record_symbol(&mut self, id: &SymbolIdentifier) {
+    self.symbols.insert(id.id.globalId);
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -169,4 +248,84 @@ mod tests {
     assert!(String::from("foo").serialize(&mut s).is_ok());
     assert_eq!(s.as_slice().len(), (2 + 1) + (2 + 4) + (2 + 4 + 3));
   }
+
+  #[test]
+  fn test_positions_stay_out_of_wire_format() {
+    use crate::ast::{Expr, Factory, Position, Symbols};
+
+    let f = Factory::new();
+    let id = f.SymbolIdentifier(f.Identifier("f".into(), 1, 0), vec![]);
+    let body: Expr = f.BooleanLiteral(true).into();
+    let fd = f.FunDef(id, vec![], vec![], f.BooleanType().into(), body, vec![]);
+    let mut positions = types::Map::new();
+    let pos = Position {
+      file: "lib.rs".into(),
+      line_from: 1,
+      col_from: 2,
+      line_to: 1,
+      col_to: 3,
+    };
+    positions.insert(id.id.globalId, pos);
+    let symbols = Symbols::new(vec![], vec![fd], vec![]).with_positions(positions);
+
+    let mut s = BufferSerializer::new();
+    assert!(symbols.serialize(&mut s).is_ok());
+
+    // Inox' encoding of the symbols `def f: Boolean = true`, spelled out byte by byte
+    #[rustfmt::skip]
+    let inner: &[u8] = &[
+      // (functions, sorts, classes)
+      6, 3,
+      2, 1,
+      // FunDef(id, tparams, params, returnType, fullBody, flags)
+      98,
+      // SymbolIdentifier written as (globalId, id, symbol path, symbol id)
+      145, 6, 4, 5, 4, 0, 0, 0, 1, 5, 4, 0, 0, 0, 0, 2, 0, 5, 4, 0, 0, 0, 1,
+      2, 0,
+      2, 0,
+      // BooleanType()
+      76,
+      // BooleanLiteral(true)
+      23, 5, 0, 1,
+      2, 0,
+      2, 0,
+      2, 0,
+    ];
+    let expected = [&[7, 5, 4, 0, 0, 0, inner.len() as u8][..], inner].concat();
+    assert_eq!(s.as_slice(), expected.as_slice());
+  }
+
+  #[test]
+  fn test_overriding_symbols() {
+    use crate::ast::Factory;
+
+    let f = Factory::new();
+    let id = f.SymbolIdentifier(f.Identifier("law".into(), 2, 0), vec![]);
+    let mut overrides = types::Map::new();
+    overrides.insert(2, 1);
+
+    // Written as (globalId, id, symbol path, symbol id)
+    let mut s = SymbolsSerializer::new(&overrides);
+    assert!(id.serialize(&mut s).is_ok());
+    #[rustfmt::skip]
+    let expected: &[u8] = &[
+      145, 6, 4, 5, 4, 0, 0, 0, 2, 5, 4, 0, 0, 0, 0, 2, 0, 5, 4, 0, 0, 0, 1,
+    ];
+    assert_eq!(s.buffer.as_slice(), expected);
+  }
+
+  #[test]
+  fn test_collect_symbols() {
+    use crate::ast::{Expr, Factory};
+
+    let f = Factory::new();
+    let id = |name: &str, gid| f.SymbolIdentifier(f.Identifier(name.into(), gid, 0), vec![]);
+    let arg: Expr = f.Int32Literal(1).into();
+    let call: Expr = f.FunctionInvocation(id("foo", 1), vec![], vec![arg]).into();
+    let x = &*f.Variable(id("x", 2), f.Int32Type().into(), vec![]);
+    let expr: Expr = f.Let(f.ValDef(x), call, x.into()).into();
+
+    let expected: types::Set<types::Int> = vec![1, 2].into_iter().collect();
+    assert_eq!(SymbolCollector::collect(&expr), expected);
+  }
 }
@@ -0,0 +1,316 @@
+use super::literal::Literal;
+use super::std_items::StdItem::*;
+use super::*;
+
+use rustc_hair::hair::ExprRef;
+use rustc_middle::ty::subst::SubstsRef;
+use rustc_middle::ty::{AdtDef, TyKind};
+
+use stainless_data::ast as st;
+
+// Rust's arithmetic operators panic on overflow in debug builds and wrap around in release
+// builds, which is what Stainless' arithmetic does with and without `--strict-arithmetic`,
+// respectively. Plain operators are therefore extracted as they are, and it is up to the backend
+// to check them for overflows or not.
+//
+// The methods of integer types that handle overflows explicitly, like `checked_add`, and the
+// operators of `std::num::Wrapping` are extracted as calls to synthetic functions that spell out
+// their precise semantics. These functions detect overflows by computing the exact result at
+// twice the width, and are flagged as `Wrapping`, so that Stainless never checks their own
+// operations for overflows.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(super) enum ArithOp {
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Rem,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(super) enum OverflowMode {
+  Wrapping,
+  Checked,
+  Saturating,
+  Overflowing,
+}
+
+/// Identifies the synthetic function for an operation on integers of some signedness and width
+pub(super) type OverflowFnKey = (ArithOp, OverflowMode, bool, u64);
+
+impl ArithOp {
+  fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "add" => Some(ArithOp::Add),
+      "sub" => Some(ArithOp::Sub),
+      "mul" => Some(ArithOp::Mul),
+      "div" => Some(ArithOp::Div),
+      "rem" => Some(ArithOp::Rem),
+      _ => None,
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      ArithOp::Add => "add",
+      ArithOp::Sub => "sub",
+      ArithOp::Mul => "mul",
+      ArithOp::Div => "div",
+      ArithOp::Rem => "rem",
+    }
+  }
+
+  fn apply<'l>(self, f: &'l st::Factory, lhs: st::Expr<'l>, rhs: st::Expr<'l>) -> st::Expr<'l> {
+    match self {
+      ArithOp::Add => f.Plus(lhs, rhs).into(),
+      ArithOp::Sub => f.Minus(lhs, rhs).into(),
+      ArithOp::Mul => f.Times(lhs, rhs).into(),
+      ArithOp::Div => f.Division(lhs, rhs).into(),
+      ArithOp::Rem => f.Remainder(lhs, rhs).into(),
+    }
+  }
+}
+
+impl OverflowMode {
+  fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "wrapping" => Some(OverflowMode::Wrapping),
+      "checked" => Some(OverflowMode::Checked),
+      "saturating" => Some(OverflowMode::Saturating),
+      "overflowing" => Some(OverflowMode::Overflowing),
+      _ => None,
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      OverflowMode::Wrapping => "wrapping",
+      OverflowMode::Checked => "checked",
+      OverflowMode::Saturating => "saturating",
+      OverflowMode::Overflowing => "overflowing",
+    }
+  }
+}
+
+impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
+  /// Whether the given ADT is `std::num::Wrapping`, which is erased to the integer it wraps.
+  pub(super) fn is_wrapping(&self, adt_def: &'tcx AdtDef) -> bool {
+    self.std_items.def_to_item_opt(adt_def.did) == Some(WrappingType)
+  }
+
+  /// Extract the function performing an operation in the given overflow mode.
+  fn extract_overflow_fn(&mut self, key: OverflowFnKey) -> &'l st::FunDef<'l> {
+    if let Some(fd) = self.with_extraction(|xt| xt.overflow_fns.get(&key).copied()) {
+      return fd;
+    }
+
+    let f = self.factory();
+    let (op, mode, signed, width) = key;
+    let type_name = format!("{}{}", if signed { "i" } else { "u" }, width);
+    let fn_name = format!("{}_{}", mode.name(), op.name());
+    let path = [type_name.as_str(), fn_name.as_str()];
+    let var = |xtor: &mut Self, name: &str, tpe| xtor.model_var(&[path[0], path[1], name], tpe);
+
+    let tpe: st::Type<'l> = f.BVType(signed, width as i32).into();
+    let wide_tpe: st::Type<'l> = f.BVType(signed, 2 * width as i32).into();
+    let lhs = var(self, "lhs", tpe);
+    let rhs = var(self, "rhs", tpe);
+    let (a, b): (st::Expr<'l>, st::Expr<'l>) = (lhs.into(), rhs.into());
+    let widen = |expr| -> st::Expr<'l> { f.BVWideningCast(expr, wide_tpe).into() };
+
+    let (min, max) = if signed {
+      (1u128 << (width - 1), (1u128 << (width - 1)) - 1)
+    } else {
+      (0, std::u128::MAX >> (128 - width))
+    };
+    let min = Literal::bv(signed, width, min).as_st_literal(f);
+    let max = Literal::bv(signed, width, max).as_st_literal(f);
+
+    // The result modulo 2^width, and the exact result, which always fits into twice the width
+    let wrapped = op.apply(f, a, b);
+    let exact = op.apply(f, widen(a), widen(b));
+    let too_large = f.GreaterThan(exact, widen(max)).into();
+    let too_small = f.LessThan(exact, widen(min)).into();
+    // Division only overflows for `MIN / -1`, whose remainder overflows along with it
+    let min_by_minus_one = || -> st::Expr<'l> {
+      let minus_one = Literal::bv(signed, width, std::u128::MAX >> (128 - width)).as_st_literal(f);
+      f.And(vec![f.Equals(a, min).into(), f.Equals(b, minus_one).into()])
+        .into()
+    };
+    let (too_small, too_large) = match (signed, op) {
+      (true, ArithOp::Div) | (true, ArithOp::Rem) => (None, Some(min_by_minus_one())),
+      (false, ArithOp::Div) | (false, ArithOp::Rem) => (None, None),
+      (true, _) => (Some(too_small), Some(too_large)),
+      (false, ArithOp::Sub) => (Some(f.LessThan(a, b).into()), None),
+      (false, _) => (None, Some(too_large)),
+    };
+    let overflows = match (too_small, too_large) {
+      (Some(too_small), Some(too_large)) => f.Or(vec![too_small, too_large]).into(),
+      (Some(cond), None) | (None, Some(cond)) => cond,
+      (None, None) => f.BooleanLiteral(false).into(),
+    };
+
+    let (return_tpe, body): (st::Type<'l>, st::Expr<'l>) = match mode {
+      OverflowMode::Wrapping => (tpe, wrapped),
+      OverflowMode::Checked => {
+        let option = self.extract_adt(self.std_items.item_to_def(OptionType));
+        let cons = |name: &str| {
+          option
+            .constructors
+            .iter()
+            .find(|cons| cons.id.id.name == name)
+            .unwrap_or_else(|| panic!("Model constructor {} is missing", name))
+            .id
+        };
+        let none = f.ADT(cons("None"), vec![tpe], vec![]).into();
+        let some = f.ADT(cons("Some"), vec![tpe], vec![wrapped]).into();
+        let return_tpe = f.ADTType(option.id, vec![tpe]).into();
+        // Unlike the other modes, checked division returns `None` rather than panicking on zero
+        let fails = match op {
+          ArithOp::Div | ArithOp::Rem => {
+            let zero = Literal::bv(signed, width, 0).as_st_literal(f);
+            f.Or(vec![f.Equals(b, zero).into(), overflows]).into()
+          }
+          _ => overflows,
+        };
+        (return_tpe, f.IfExpr(fails, none, some).into())
+      }
+      OverflowMode::Saturating => {
+        let mut saturated = wrapped;
+        if let Some(too_small) = too_small {
+          saturated = f.IfExpr(too_small, min, saturated).into();
+        }
+        if let Some(too_large) = too_large {
+          saturated = f.IfExpr(too_large, max, saturated).into();
+        }
+        (tpe, saturated)
+      }
+      OverflowMode::Overflowing => {
+        let return_tpe = f.TupleType(vec![tpe, f.BooleanType().into()]).into();
+        (return_tpe, f.Tuple(vec![wrapped, overflows]).into())
+      }
+    };
+
+    let fun_id = self.model_id(&path);
+    let fd = f.FunDef(
+      fun_id,
+      vec![],
+      vec![f.ValDef(lhs), f.ValDef(rhs)],
+      return_tpe,
+      body,
+      vec![f.Synthetic().into(), f.Wrapping().into()],
+    );
+    self.add_function(fun_id, fd);
+    self.with_extraction_mut(|xt| xt.overflow_fns.insert(key, fd));
+    fd
+  }
+}
+
+impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
+  /// Extract calls to integer methods like `checked_add`, and operators on `Wrapping`, as calls
+  /// to the synthetic function for the corresponding operation and overflow mode.
+  pub(super) fn try_extract_overflow_call(
+    &mut self,
+    def_id: DefId,
+    substs: SubstsRef<'tcx>,
+    args: &[ExprRef<'tcx>],
+    span: Span,
+  ) -> Option<st::Expr<'l>> {
+    let tcx = self.tcx();
+    // Negation is extracted as the subtraction `0 - x`, which overflows in the same cases
+    let (op, negate, mode, operand_ty) = match tcx.trait_of_item(def_id) {
+      // Operators on `Wrapping<T>` wrap around
+      Some(trait_def_id) => {
+        let operand_ty = match substs.type_at(0).kind {
+          TyKind::Adt(adt_def, substs) if self.base.is_wrapping(adt_def) => substs.type_at(0),
+          _ => return None,
+        };
+        let (op, negate) = match self.base.std_items.def_to_item_opt(trait_def_id) {
+          Some(AddTrait) => (ArithOp::Add, false),
+          Some(SubTrait) => (ArithOp::Sub, false),
+          Some(MulTrait) => (ArithOp::Mul, false),
+          Some(DivTrait) => (ArithOp::Div, false),
+          Some(RemTrait) => (ArithOp::Rem, false),
+          Some(NegTrait) => (ArithOp::Sub, true),
+          _ if self.is_unsupported_wrapping_op(trait_def_id) => {
+            let msg = format!(
+              "Cannot extract {} on Wrapping",
+              tcx.def_path_str(trait_def_id)
+            );
+            return Some(self.unsupported_expr(span, msg));
+          }
+          _ => return None,
+        };
+        (op, negate, OverflowMode::Wrapping, operand_ty)
+      }
+
+      // Inherent methods of integer types, named after their overflow mode and operation
+      None => {
+        let impl_def_id = tcx.impl_of_method(def_id)?;
+        let self_ty = tcx.type_of(impl_def_id);
+        if !self.base.is_bv_type(self_ty) {
+          return None;
+        }
+        let name = tcx.item_name(def_id).as_str();
+        let mut parts = name.splitn(2, '_');
+        let mode = OverflowMode::from_name(parts.next()?)?;
+        let op_name = parts.next().unwrap_or_default();
+        let (op, negate) = match (op_name, ArithOp::from_name(op_name)) {
+          ("neg", _) => (ArithOp::Sub, true),
+          (_, Some(op)) => (op, false),
+          _ => {
+            let msg = format!("Cannot extract overflow-handling method {}", name);
+            return Some(self.unsupported_expr(span, msg));
+          }
+        };
+        (op, negate, mode, self_ty)
+      }
+    };
+
+    let (signed, width) = match self.base.bv_type_params(operand_ty) {
+      Some(params) => params,
+      None => {
+        let msg = format!("Cannot extract wrapping arithmetic on {}", operand_ty);
+        return Some(self.unsupported_expr(span, msg));
+      }
+    };
+    let fd = self.base.extract_overflow_fn((op, mode, signed, width));
+    let mut args = self.extract_expr_refs(args.to_vec());
+    if negate {
+      let zero = Literal::bv(signed, width, 0).as_st_literal(self.factory());
+      args.insert(0, zero);
+    }
+    Some(
+      self
+        .factory()
+        .FunctionInvocation(fd.id, vec![], args)
+        .into(),
+    )
+  }
+
+  /// Whether the trait is one of the compound assignment, bitwise or shift operators, which
+  /// `Wrapping` implements but which we don't extract.
+  fn is_unsupported_wrapping_op(&self, trait_def_id: DefId) -> bool {
+    let lang_items = self.tcx().lang_items();
+    [
+      lang_items.add_assign_trait(),
+      lang_items.sub_assign_trait(),
+      lang_items.mul_assign_trait(),
+      lang_items.div_assign_trait(),
+      lang_items.rem_assign_trait(),
+      lang_items.not_trait(),
+      lang_items.bitand_trait(),
+      lang_items.bitor_trait(),
+      lang_items.bitxor_trait(),
+      lang_items.shl_trait(),
+      lang_items.shr_trait(),
+      lang_items.bitand_assign_trait(),
+      lang_items.bitor_assign_trait(),
+      lang_items.bitxor_assign_trait(),
+      lang_items.shl_assign_trait(),
+      lang_items.shr_assign_trait(),
+    ]
+    .contains(&Some(trait_def_id))
+  }
+}
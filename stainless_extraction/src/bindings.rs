@@ -20,7 +20,13 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     }
 
     // Bindings from the body
-    BindingsCollector::run(self);
+    let body = self.body;
+    BindingsCollector::run(self, body);
+  }
+
+  /// Add the bindings of a closure's body to the DefContext
+  pub(super) fn populate_closure_def_context(&mut self, body: &'tcx hir::Body<'tcx>) {
+    BindingsCollector::run(self, body);
   }
 
   /// Extract a binding based on the binding node's HIR id.
@@ -45,13 +51,12 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
           {
             match self.tables.extract_binding_mode(tcx.sess, *hir_id, *span) {
               // allowed binding modes
-              Some(ty::BindByValue(hir::Mutability::Not))
-              | Some(ty::BindByReference(hir::Mutability::Not)) => ident,
+              Some(ty::BindByValue(_)) | Some(ty::BindByReference(hir::Mutability::Not)) => ident,
 
               // For the forbidden binding modes, return the identifier anyway
               // because failure will occur later.
               _ => {
-                xtor.unsupported(*span, "Only immutable bindings are supported");
+                xtor.unsupported(*span, "Mutable reference bindings are not supported");
                 ident
               }
             }
@@ -112,8 +117,7 @@ struct BindingsCollector<'bxtor, 'a, 'l, 'tcx> {
 }
 
 impl<'bxtor, 'a, 'l, 'tcx> BindingsCollector<'bxtor, 'a, 'l, 'tcx> {
-  fn run(bxtor: &'bxtor mut BodyExtractor<'a, 'l, 'tcx>) -> Self {
-    let body = bxtor.body;
+  fn run(bxtor: &'bxtor mut BodyExtractor<'a, 'l, 'tcx>, body: &'tcx hir::Body<'tcx>) -> Self {
     assert!(body.generator_kind.is_none());
 
     let mut this = Self { bxtor };
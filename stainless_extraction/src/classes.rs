@@ -0,0 +1,492 @@
+use super::*;
+
+use rustc_hair::hair::ExprRef;
+use rustc_middle::traits::Vtable;
+use rustc_middle::ty::subst::Subst;
+use rustc_middle::ty::{self, AssocKind, List, PredicateKind, SubstsRef};
+use rustc_trait_selection::traits::{Obligation, ObligationCause, SelectionContext};
+
+use crate::flags::Flag;
+use stainless_data::ast as st;
+
+/// Extraction of traits as type classes
+
+// A user-defined trait becomes an abstract class whose first type parameter is the trait's
+// `Self` type, and every `impl Trait for Type` becomes a concrete class extending it. Trait
+// bounds on such traits are satisfied by passing around instances of these classes, the
+// so-called evidence, which is also the receiver of every call to a trait method. For instance,
+//   fn combine<T: Monoid>(a: &T, b: &T) -> T { a.combine(b) }
+// is extracted to something along the lines of
+//   def combine[T](a: T, b: T, ev: Monoid[T]): T = ev.combine(a, b)
+//
+// Laws are boolean methods of a trait flagged with `#[law]`. Every impl gets its own version of
+// each law, which calls the trait's law on `super` and has to hold for the impl.
+
+impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
+  /// Find the impl satisfying a trait bound in the context of the given item, along with the
+  /// impl's type arguments.
+  pub(super) fn select_impl(
+    &self,
+    def_id: DefId,
+    trait_ref: ty::TraitRef<'tcx>,
+  ) -> Option<(DefId, SubstsRef<'tcx>)> {
+    let param_env = self.tcx.param_env(def_id);
+    self.tcx.infer_ctxt().enter(|infcx| {
+      let mut selcx = SelectionContext::new(&infcx);
+      let predicate = ty::Binder::dummy(trait_ref).to_poly_trait_predicate();
+      let obligation = Obligation::new(ObligationCause::dummy(), param_env, predicate);
+      match selcx.select(&obligation) {
+        Ok(Some(Vtable::VtableImpl(data))) => Some((
+          data.impl_def_id,
+          infcx.resolve_vars_if_possible(&data.substs),
+        )),
+        _ => None,
+      }
+    })
+  }
+
+  /// Extract a user-defined trait as an abstract class
+  pub(super) fn extract_trait(&mut self, def_id: DefId) -> &'l st::ClassDef<'l> {
+    let f = self.factory();
+    let class_id = self.get_or_register_def(def_id);
+
+    let has_supertraits =
+      self
+        .tcx
+        .super_predicates_of(def_id)
+        .predicates
+        .iter()
+        .any(|(predicate, _)| match predicate.kind() {
+          PredicateKind::Trait(data, _) => self.is_user_trait(data.def_id()),
+          _ => false,
+        });
+    if has_supertraits {
+      self.unsupported(self.tcx.def_span(def_id), "Cannot extract supertraits");
+    }
+
+    let (tparams, _) = self.extract_generics(def_id);
+    self.set_pos(class_id, self.tcx.def_span(def_id));
+    f.ClassDef(
+      class_id,
+      tparams,
+      vec![],
+      vec![],
+      vec![f.IsAbstract().into()],
+    )
+  }
+
+  /// Extract an impl of a user-defined trait as a concrete class extending the trait's class.
+  /// The evidence for the impl's own trait bounds is stored in the class' fields. Alongside the
+  /// class, we return the trait's laws that the impl inherits, which have to hold for it.
+  pub(super) fn extract_trait_impl(
+    &mut self,
+    def_id: DefId,
+  ) -> (&'l st::ClassDef<'l>, Vec<&'l st::FunDef<'l>>) {
+    let f = self.factory();
+    let span = self.tcx.def_span(def_id);
+    let class_id = self.get_or_register_def(def_id);
+    let (tparams, txtcx) = self.extract_generics(def_id);
+
+    let trait_ref = self.tcx.impl_trait_ref(def_id).unwrap();
+    let parent = self.extract_evidence_class_type(trait_ref, &txtcx, span);
+
+    let substs = List::identity_for_item(self.tcx, def_id);
+    let fields = self
+      .evidence_bounds(def_id, substs)
+      .into_iter()
+      .map(|trait_ref| &*f.ValDef(self.extract_evidence_var(trait_ref, &txtcx, span)))
+      .collect();
+
+    self.set_pos(class_id, span);
+    let cd = f.ClassDef(class_id, tparams, vec![parent], fields, vec![]);
+    let laws = self.extract_inherited_laws(def_id, &txtcx);
+    (cd, laws)
+  }
+
+  /// Synthesize the laws an impl inherits from its trait without overriding them. Each one
+  /// simply defers to the trait's law, but is verified for this particular impl.
+  fn extract_inherited_laws(
+    &mut self,
+    impl_def_id: DefId,
+    txtcx: &TyExtractionCtxt<'l>,
+  ) -> Vec<&'l st::FunDef<'l>> {
+    let f = self.factory();
+    let tcx = self.tcx;
+    let span = tcx.def_span(impl_def_id);
+    let class_id = self.get_or_register_def(impl_def_id);
+    let trait_ref = tcx.impl_trait_ref(impl_def_id).unwrap();
+
+    // Laws with type parameters of their own are rejected when extracting the trait
+    let impl_items = tcx.associated_items(impl_def_id);
+    let laws: Vec<DefId> = tcx
+      .associated_items(trait_ref.def_id)
+      .in_definition_order()
+      .filter(|item| item.kind == AssocKind::Fn && self.is_law(item.def_id))
+      .filter(|item| tcx.generics_of(item.def_id).own_counts().types == 0)
+      .filter(|item| {
+        impl_items
+          .filter_by_name_unhygienic(item.ident.name)
+          .next()
+          .is_none()
+      })
+      .map(|item| item.def_id)
+      .collect();
+
+    laws
+      .into_iter()
+      .map(|law_def_id| {
+        let law_id = self.extract_fn_ref(law_def_id);
+        let poly_fn_sig = tcx.fn_sig(law_def_id).subst(tcx, trait_ref.substs);
+        let fn_sig = tcx.liberate_late_bound_regions(law_def_id, &poly_fn_sig);
+        let params: Params<'l> = tcx
+          .fn_arg_names(law_def_id)
+          .iter()
+          .zip(fn_sig.inputs())
+          .map(|(ident, &ty)| {
+            let id = self.fresh_id(ident.to_string());
+            let tpe = self.extract_ty(ty, txtcx, span);
+            &*f.ValDef(f.Variable(id, tpe, vec![]))
+          })
+          .collect();
+
+        let body = self.extract_super_law_call(impl_def_id, law_def_id, txtcx, &params);
+        let holds = &*f.Variable(
+          self.fresh_id("holds".into()),
+          f.BooleanType().into(),
+          vec![],
+        );
+        let body = f.Ensuring(body, f.Lambda(vec![f.ValDef(holds)], holds.into()));
+
+        let name = tcx.item_name(law_def_id).to_string();
+        let fun_id = self.with_extraction_mut(|xt| xt.fresh_overriding_id(name, law_id));
+        let flags = vec![
+          f.Law().into(),
+          f.Derived(law_id).into(),
+          f.IsMethodOf(class_id).into(),
+        ];
+        &*f.FunDef(
+          fun_id,
+          vec![],
+          params,
+          f.BooleanType().into(),
+          body.into(),
+          flags,
+        )
+      })
+      .collect()
+  }
+
+  /// Invoke the trait's version of a law on behalf of an impl of that trait.
+  pub(super) fn extract_super_law_call(
+    &mut self,
+    impl_def_id: DefId,
+    law_def_id: DefId,
+    txtcx: &TyExtractionCtxt<'l>,
+    params: &[&'l st::ValDef<'l>],
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    let span = self.tcx.def_span(impl_def_id);
+    let trait_ref = self.tcx.impl_trait_ref(impl_def_id).unwrap();
+    let parent = self.extract_evidence_class_type(trait_ref, txtcx, span);
+    let law_id = self.extract_fn_ref(law_def_id);
+    let args = params.iter().map(|vd| vd.v.into()).collect();
+    f.MethodInvocation(f.Super(parent).into(), law_id, vec![], args)
+      .into()
+  }
+
+  /// Report laws that cannot be verified against the impls of their trait. Laws are methods of
+  /// user-defined traits that return a boolean and have no type parameters of their own.
+  pub(super) fn check_law(&self, def_id: DefId) {
+    let tcx = self.tcx;
+    let span = tcx.def_span(def_id);
+    let in_trait = tcx
+      .trait_of_item(def_id)
+      .map_or(false, |did| self.is_user_trait(did));
+
+    if !in_trait && self.overridden_law(def_id).is_none() {
+      self.unsupported(span, "Laws can only be declared in traits");
+    } else if in_trait && tcx.generics_of(def_id).own_counts().types > 0 {
+      self.unsupported(span, "Laws cannot have type parameters");
+    }
+    if !tcx.fn_sig(def_id).skip_binder().output().is_bool() {
+      self.unsupported(span, "Laws must return bool");
+    }
+  }
+
+  /// The law of a user-defined trait that the given method of an impl overrides, if any.
+  pub(super) fn overridden_law(&self, def_id: DefId) -> Option<DefId> {
+    self
+      .overridden_method(def_id)
+      .filter(|&did| self.is_law(did))
+  }
+
+  fn is_law(&self, def_id: DefId) -> bool {
+    def_id.as_local().map_or(false, |local_def_id| {
+      let hir_id = self.tcx.hir().as_local_hir_id(local_def_id);
+      self.has_carrier_flag(hir_id, Flag::Law)
+    })
+  }
+
+  /// Extract a method of a trait that doesn't provide a default implementation
+  pub(super) fn extract_abstract_fn(&mut self, def_id: DefId) -> &'l st::FunDef<'l> {
+    let f = self.factory();
+    let span = self.tcx.def_span(def_id);
+    let trait_def_id = self.tcx.trait_of_item(def_id).unwrap();
+    let class_id = self.get_or_register_def(trait_def_id);
+
+    let (tparams, txtcx) = self.extract_method_generics(def_id);
+    let (evidence_params, _) = self.extract_evidence_env(def_id, &txtcx);
+
+    let poly_fn_sig = self.tcx.fn_sig(def_id);
+    let fn_sig = self.tcx.liberate_late_bound_regions(def_id, &poly_fn_sig);
+    let params: Params<'l> = self
+      .tcx
+      .fn_arg_names(def_id)
+      .iter()
+      .zip(fn_sig.inputs())
+      .map(|(ident, &ty)| {
+        let id = self.fresh_id(ident.to_string());
+        let tpe = self.extract_ty(ty, &txtcx, span);
+        &*f.ValDef(f.Variable(id, tpe, vec![]))
+      })
+      .chain(evidence_params)
+      .collect();
+    let return_tpe = self.extract_ty(fn_sig.output(), &txtcx, span);
+
+    let flags = vec![f.IsAbstract().into(), f.IsMethodOf(class_id).into()];
+    let fun_id = self.extract_fn_ref(def_id);
+    self.set_pos(fun_id, span);
+    f.FunDef(
+      fun_id,
+      tparams,
+      params,
+      return_tpe,
+      f.NoTree(return_tpe).into(),
+      flags,
+    )
+  }
+
+  /// Extract the generics of a function, leaving out the type parameters it shares with its
+  /// class, if it is a method.
+  pub(super) fn extract_method_generics(
+    &mut self,
+    def_id: DefId,
+  ) -> (Vec<&'l st::TypeParameterDef<'l>>, TyExtractionCtxt<'l>) {
+    let (tparams, txtcx) = self.extract_generics(def_id);
+    if self.class_of_method(def_id).is_some() {
+      let parent_count = self.tcx.generics_of(def_id).parent_count as u32;
+      (self.tparam_defs(&txtcx, parent_count), txtcx)
+    } else {
+      (tparams, txtcx)
+    }
+  }
+
+  /// Create the evidence parameters of a function and collect all evidence available in its
+  /// body, including the one provided by its class, if it is a method.
+  pub(super) fn extract_evidence_env(
+    &mut self,
+    def_id: DefId,
+    txtcx: &TyExtractionCtxt<'l>,
+  ) -> (Params<'l>, Evidence<'l, 'tcx>) {
+    let f = self.factory();
+    let tcx = self.tcx;
+    let span = tcx.def_span(def_id);
+    let mut evidence = vec![];
+
+    if let Some(class_def_id) = self.class_of_method(def_id) {
+      let class_id = self.get_or_register_def(class_def_id);
+      let tps = tcx
+        .generics_of(class_def_id)
+        .params
+        .iter()
+        .filter_map(|param| txtcx.index_to_tparam.get(&param.index))
+        .map(|tparam| tparam.into())
+        .collect();
+      let this: st::Expr<'l> = f.This(f.ClassType(class_id, tps)).into();
+
+      match tcx.trait_of_item(def_id) {
+        // Within a trait, `this` is the evidence for `Self` implementing the trait
+        Some(trait_def_id) => evidence.push((ty::TraitRef::identity(tcx, trait_def_id), this)),
+        // Within an impl, the evidence for the impl's bounds is found in the class' fields
+        None => {
+          let class_def = self.with_extraction(|xt| xt.classes[class_id]);
+          let substs = List::identity_for_item(tcx, class_def_id);
+          let bounds = self.evidence_bounds(class_def_id, substs);
+          for (trait_ref, field) in bounds.into_iter().zip(class_def.fields.iter()) {
+            evidence.push((trait_ref, f.ClassSelector(this, field.v.id).into()));
+          }
+        }
+      }
+    }
+
+    let substs = List::identity_for_item(tcx, def_id);
+    let params = self
+      .evidence_bounds(def_id, substs)
+      .into_iter()
+      .map(|trait_ref| {
+        let var = self.extract_evidence_var(trait_ref, txtcx, span);
+        evidence.push((trait_ref, var.into()));
+        &*f.ValDef(var)
+      })
+      .collect();
+
+    (params, evidence)
+  }
+
+  /// The bounds on user-defined traits of the given item, for which evidence is passed around.
+  /// Methods of classes only take evidence for their own bounds, since their class provides the
+  /// rest.
+  pub(super) fn evidence_bounds(
+    &self,
+    def_id: DefId,
+    substs: SubstsRef<'tcx>,
+  ) -> Vec<ty::TraitRef<'tcx>> {
+    let predicates = self.tcx.predicates_of(def_id);
+    let predicates = if self.class_of_method(def_id).is_some() {
+      predicates.instantiate_own(self.tcx, substs)
+    } else {
+      predicates.instantiate(self.tcx, substs)
+    };
+    predicates
+      .predicates
+      .into_iter()
+      .filter_map(|predicate| match predicate.kind() {
+        PredicateKind::Trait(data, _) if self.is_user_trait(data.def_id()) => {
+          Some(self.tcx.erase_regions(&data.skip_binder().trait_ref))
+        }
+        _ => None,
+      })
+      .collect()
+  }
+
+  fn extract_evidence_var(
+    &mut self,
+    trait_ref: ty::TraitRef<'tcx>,
+    txtcx: &TyExtractionCtxt<'l>,
+    span: Span,
+  ) -> &'l st::Variable<'l> {
+    let f = self.factory();
+    let tpe = self.extract_evidence_class_type(trait_ref, txtcx, span);
+    let id = self.fresh_id("ev".into());
+    f.Variable(id, tpe.into(), vec![])
+  }
+
+  fn extract_evidence_class_type(
+    &mut self,
+    trait_ref: ty::TraitRef<'tcx>,
+    txtcx: &TyExtractionCtxt<'l>,
+    span: Span,
+  ) -> &'l st::ClassType<'l> {
+    let class_id = self.get_or_register_def(trait_ref.def_id);
+    let tps = self.extract_tys(trait_ref.substs.types(), txtcx, span);
+    self.factory().ClassType(class_id, tps)
+  }
+
+  /// The class a local function belongs to, if any. This is either a user-defined trait or an
+  /// impl of one.
+  pub(super) fn class_of_method(&self, def_id: DefId) -> Option<DefId> {
+    let tcx = self.tcx;
+    match tcx.trait_of_item(def_id) {
+      Some(trait_def_id) => Some(trait_def_id).filter(|&did| self.is_user_trait(did)),
+      None => tcx.impl_of_method(def_id).filter(|&impl_def_id| {
+        tcx
+          .trait_id_of_impl(impl_def_id)
+          .map_or(false, |did| self.is_user_trait(did))
+      }),
+    }
+  }
+
+  /// The method of a user-defined trait that the given method of an impl implements, if any.
+  pub(super) fn overridden_method(&self, def_id: DefId) -> Option<DefId> {
+    let tcx = self.tcx;
+    let impl_def_id = tcx.impl_of_method(def_id)?;
+    let trait_def_id = tcx
+      .trait_id_of_impl(impl_def_id)
+      .filter(|&did| self.is_user_trait(did))?;
+    tcx
+      .associated_items(trait_def_id)
+      .filter_by_name_unhygienic(tcx.item_name(def_id))
+      .find(|item| item.kind == AssocKind::Fn)
+      .map(|item| item.def_id)
+  }
+
+  pub(super) fn is_user_trait(&self, def_id: DefId) -> bool {
+    def_id.is_local()
+  }
+}
+
+impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
+  /// Extract a call to a method of a user-defined trait as a method invocation on the evidence
+  /// of the trait being implemented.
+  pub(super) fn extract_trait_method_call(
+    &mut self,
+    trait_def_id: DefId,
+    def_id: DefId,
+    substs: SubstsRef<'tcx>,
+    args: &[ExprRef<'tcx>],
+    span: Span,
+  ) -> st::Expr<'l> {
+    let tcx = self.tcx();
+    let trait_ref = ty::TraitRef::from_method(tcx, trait_def_id, substs);
+    let receiver = self.extract_evidence(trait_ref, span);
+    let fun_id = self.base.extract_fn_ref(def_id);
+
+    // Only pass the method's own type arguments, the trait's are those of the receiver
+    let num_trait_tys = tcx.generics_of(trait_def_id).own_counts().types;
+    let tps = self
+      .base
+      .extract_tys(substs.types().skip(num_trait_tys), &self.txtcx, span);
+
+    let mut args = self.extract_expr_refs(args.to_vec());
+    args.extend(self.extract_evidence_args(def_id, substs, span));
+    self
+      .factory()
+      .MethodInvocation(receiver, fun_id, tps, args)
+      .into()
+  }
+
+  /// Extract the evidence a call to the given function has to pass along.
+  pub(super) fn extract_evidence_args(
+    &mut self,
+    def_id: DefId,
+    substs: SubstsRef<'tcx>,
+    span: Span,
+  ) -> Vec<st::Expr<'l>> {
+    self
+      .base
+      .evidence_bounds(def_id, substs)
+      .into_iter()
+      .map(|trait_ref| self.extract_evidence(trait_ref, span))
+      .collect()
+  }
+
+  /// Find the evidence for a trait bound, either among the evidence available in the current
+  /// body, or by instantiating the class of the impl that satisfies it.
+  fn extract_evidence(&mut self, trait_ref: ty::TraitRef<'tcx>, span: Span) -> st::Expr<'l> {
+    let f = self.factory();
+    let tcx = self.tcx();
+    let trait_ref = tcx.erase_regions(&trait_ref);
+
+    if let Some((_, evidence)) = self.evidence.iter().find(|(bound, _)| *bound == trait_ref) {
+      return *evidence;
+    }
+
+    match self.base.select_impl(self.txtcx.def_id, trait_ref) {
+      Some((impl_def_id, substs)) if impl_def_id.is_local() => {
+        let class_id = self.base.get_or_register_def(impl_def_id);
+        let tps = self.extract_arg_types(substs, span);
+        let args = self
+          .base
+          .evidence_bounds(impl_def_id, substs)
+          .into_iter()
+          .map(|trait_ref| self.extract_evidence(trait_ref, span))
+          .collect();
+        f.ClassConstructor(f.ClassType(class_id, tps), args).into()
+      }
+      _ => self.unsupported_expr(
+        span,
+        format!("Cannot find evidence for trait bound {}", trait_ref),
+      ),
+    }
+  }
+}
@@ -3,14 +3,17 @@ use super::std_items::StdItem;
 use super::std_items::StdItem::*;
 use super::*;
 
-use std::convert::TryFrom;
-
+use rustc_middle::middle::region;
 use rustc_middle::mir::{BinOp, BorrowKind, Mutability, UnOp};
-use rustc_middle::ty::{subst::SubstsRef, Ty, TyKind};
+use rustc_middle::ty::layout::IntegerExt;
+use rustc_middle::ty::{self, subst::SubstsRef, AdtDef, Ty, TyKind, TypeFoldable};
+use rustc_span::hygiene::{DesugaringKind, ExpnKind, MacroKind};
+use rustc_span::symbol::Symbol;
+use rustc_target::abi::Integer;
 
 use rustc_hair::hair::{
   Arm, BindingMode, Block, BlockSafety, Expr, ExprKind, ExprRef, FieldPat, Guard, LogicalOp,
-  Mirror, Pat, PatKind, StmtKind, StmtRef,
+  Mirror, Pat, PatKind, Stmt, StmtKind, StmtRef,
 };
 
 use stainless_data::ast as st;
@@ -21,18 +24,23 @@ type Result<T> = std::result::Result<T, &'static str>;
 impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
   pub(super) fn extract_expr(&mut self, expr: Expr<'tcx>) -> st::Expr<'l> {
     match expr.kind {
-      ExprKind::Literal { literal: konst, .. } => match Literal::try_from(konst) {
+      ExprKind::Literal { literal: konst, .. } => match self.literal(konst) {
         Ok(lit) => lit.as_st_literal(self.factory()),
+        // `Self` used as the constructor of a unit struct ends up as a zero-sized constant
+        _ if is_unit_struct(konst.ty) => self.extract_unit_struct(konst.ty, expr.span),
         _ => self.unsupported_expr(expr.span, "Unsupported kind of literal"),
       },
       ExprKind::Unary { .. } => self.extract_unary(expr),
       ExprKind::Binary { .. } => self.extract_binary(expr),
+      ExprKind::Assign { .. } => self.extract_assignment(expr),
+      ExprKind::AssignOp { .. } => self.extract_assignment_op(expr),
       ExprKind::LogicalOp { .. } => self.extract_logical_op(expr),
       ExprKind::Tuple { .. } => self.extract_tuple(expr),
       ExprKind::Field { .. } => self.extract_field(expr),
       ExprKind::VarRef { id } => self.fetch_var(id).into(),
       ExprKind::Call { ty, args, .. } => self.extract_call_like(ty, &args, expr.span),
       ExprKind::Adt { .. } => self.extract_adt_construction(expr),
+      ExprKind::Closure { .. } => self.extract_closure(expr.ty, expr.span),
       ExprKind::Block { body: ast_block } => {
         let block = self.mirror(ast_block);
         match block.safety_mode {
@@ -40,10 +48,21 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
           _ => self.unsupported_expr(expr.span, "Cannot extract unsafe block"),
         }
       }
+      // The values of `?`s are extracted ahead of time, when restructuring early exits
+      ExprKind::Match { .. } if expr.span.is_desugaring(DesugaringKind::QuestionMark) => {
+        self.extract_hoisted_try(expr.span)
+      }
+      ExprKind::Match { .. } if assertion_macro(expr.span).is_some() => {
+        self.extract_assertion(expr)
+      }
       ExprKind::Match {
         scrutinee,
         mut arms,
       } => {
+        if self.looks_like_for_loop(scrutinee.clone(), &arms) {
+          return self.extract_for_loop(scrutinee, arms, expr.span);
+        }
+
         // TODO: Avoid this clone by just looking up the type of scrutinee for looks_like_if
         let scrutinee_ = scrutinee.clone();
         match self.looks_like_if(scrutinee, &arms) {
@@ -57,9 +76,29 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
         }
       }
 
-      // TODO: Handle method calls
       // TODO: Handle arbitrary-precision integers
-      ExprKind::Scope { value, .. } => self.extract_expr_ref(value),
+      ExprKind::Scope {
+        value,
+        region_scope,
+        ..
+      } => {
+        // Loops need to know their own scope to recognize `break`s targeting them
+        let value = self.mirror(value);
+        match value.kind {
+          ExprKind::Loop { .. } => self.extract_loop(value, Some(region_scope)),
+          _ => self.extract_expr(value),
+        }
+      }
+      ExprKind::Loop { .. } => self.extract_loop(expr, None),
+      ExprKind::Break { .. } => self.unsupported_expr(
+        expr.span,
+        "Cannot extract break outside of the tail position of a loop body",
+      ),
+      ExprKind::Return { .. } => {
+        self.unsupported_expr(expr.span, "Cannot extract return in this position")
+      }
+
+      ExprKind::Cast { source } => self.extract_cast(source, expr.ty, expr.span),
       ExprKind::Use { source } => self.extract_expr_ref(source),
       ExprKind::NeverToAny { source } => self.extract_expr_ref(source),
 
@@ -72,6 +111,13 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
         arg,
       } => self.extract_expr_ref(arg),
 
+      // Borrow a value mutably. Stainless' imperative phase updates values of mutable types
+      // in place, so we erase the reference here as well and let Stainless check aliasing.
+      ExprKind::Borrow {
+        borrow_kind: BorrowKind::Mut { .. },
+        arg,
+      } => self.extract_expr_ref(arg),
+
       _ => self.unsupported_expr(
         expr.span,
         format!("Cannot extract expr kind {:?}", expr.kind),
@@ -79,12 +125,12 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     }
   }
 
-  fn extract_expr_ref(&mut self, expr: ExprRef<'tcx>) -> st::Expr<'l> {
+  pub(super) fn extract_expr_ref(&mut self, expr: ExprRef<'tcx>) -> st::Expr<'l> {
     let expr = self.mirror(expr);
     self.extract_expr(expr)
   }
 
-  fn extract_expr_refs<I>(&mut self, exprs: I) -> Vec<st::Expr<'l>>
+  pub(super) fn extract_expr_refs<I>(&mut self, exprs: I) -> Vec<st::Expr<'l>>
   where
     I: IntoIterator<Item = ExprRef<'tcx>>,
   {
@@ -115,7 +161,6 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
   }
 
   fn extract_binary(&mut self, expr: Expr<'tcx>) -> st::Expr<'l> {
-    let f = self.factory();
     if let ExprKind::Binary {
       op,
       lhs: arg1,
@@ -124,42 +169,55 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     {
       let (arg1, arg2) = (self.mirror(arg1), self.mirror(arg2));
       let (arg1_ty, arg2_ty) = (arg1.ty, arg2.ty);
-      let args_are_bv = self.base.is_bv_type(arg1_ty) && self.base.is_bv_type(arg2_ty);
-      let args_are_bool = arg1_ty.is_bool() && arg2_ty.is_bool();
-
       let (arg1, arg2) = (self.extract_expr(arg1), self.extract_expr(arg2));
-      match op {
-        _ if !args_are_bv && !args_are_bool => {
-          self.unsupported_expr(expr.span, format!("Cannot extract binary op {:?}", op))
-        }
-
-        BinOp::Eq => f.Equals(arg1, arg2).into(),
-        BinOp::Ne => f.Not(f.Equals(arg1, arg2).into()).into(),
-        BinOp::Add if args_are_bv => f.Plus(arg1, arg2).into(),
-        BinOp::Sub if args_are_bv => f.Minus(arg1, arg2).into(),
-        BinOp::Mul if args_are_bv => f.Times(arg1, arg2).into(),
-        BinOp::Div if args_are_bv => f.Division(arg1, arg2).into(),
-        BinOp::Rem if args_are_bv => f.Remainder(arg1, arg2).into(),
-        BinOp::Lt if args_are_bv => f.LessThan(arg1, arg2).into(),
-        BinOp::Le if args_are_bv => f.LessEquals(arg1, arg2).into(),
-        BinOp::Ge if args_are_bv => f.GreaterEquals(arg1, arg2).into(),
-        BinOp::Gt if args_are_bv => f.GreaterThan(arg1, arg2).into(),
-        BinOp::BitXor if args_are_bv => f.BVXor(arg1, arg2).into(),
-        BinOp::BitAnd if args_are_bv => f.BVAnd(arg1, arg2).into(),
-        BinOp::BitOr if args_are_bv => f.BVOr(arg1, arg2).into(),
-        BinOp::Shl | BinOp::Shr if args_are_bv => {
-          self.extract_shift(arg1, arg2, arg1_ty, arg2_ty, op == BinOp::Shl, expr.span)
-        }
-        _ => {
-          // TODO: Support pointer offset BinOp?
-          self.unsupported_expr(expr.span, format!("Cannot extract binary op {:?}", op))
-        }
-      }
+      self.extract_binary_op(op, arg1, arg2, arg1_ty, arg2_ty, expr.span)
     } else {
       unreachable!()
     }
   }
 
+  fn extract_binary_op(
+    &mut self,
+    op: BinOp,
+    arg1: st::Expr<'l>,
+    arg2: st::Expr<'l>,
+    arg1_ty: Ty<'tcx>,
+    arg2_ty: Ty<'tcx>,
+    span: Span,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    let args_are_bv = self.base.is_bv_type(arg1_ty) && self.base.is_bv_type(arg2_ty);
+    let args_are_bool = arg1_ty.is_bool() && arg2_ty.is_bool();
+
+    match op {
+      _ if !args_are_bv && !args_are_bool => {
+        self.unsupported_expr(span, format!("Cannot extract binary op {:?}", op))
+      }
+
+      BinOp::Eq => f.Equals(arg1, arg2).into(),
+      BinOp::Ne => f.Not(f.Equals(arg1, arg2).into()).into(),
+      BinOp::Add if args_are_bv => f.Plus(arg1, arg2).into(),
+      BinOp::Sub if args_are_bv => f.Minus(arg1, arg2).into(),
+      BinOp::Mul if args_are_bv => f.Times(arg1, arg2).into(),
+      BinOp::Div if args_are_bv => f.Division(arg1, arg2).into(),
+      BinOp::Rem if args_are_bv => f.Remainder(arg1, arg2).into(),
+      BinOp::Lt if args_are_bv => f.LessThan(arg1, arg2).into(),
+      BinOp::Le if args_are_bv => f.LessEquals(arg1, arg2).into(),
+      BinOp::Ge if args_are_bv => f.GreaterEquals(arg1, arg2).into(),
+      BinOp::Gt if args_are_bv => f.GreaterThan(arg1, arg2).into(),
+      BinOp::BitXor if args_are_bv => f.BVXor(arg1, arg2).into(),
+      BinOp::BitAnd if args_are_bv => f.BVAnd(arg1, arg2).into(),
+      BinOp::BitOr if args_are_bv => f.BVOr(arg1, arg2).into(),
+      BinOp::Shl | BinOp::Shr if args_are_bv => {
+        self.extract_shift(arg1, arg2, arg1_ty, arg2_ty, op == BinOp::Shl, span)
+      }
+      _ => {
+        // TODO: Support pointer offset BinOp?
+        self.unsupported_expr(span, format!("Cannot extract binary op {:?}", op))
+      }
+    }
+  }
+
   /// Stainless requires matching bitvector types on all operations, so we insert appropriate
   /// widenings, where possible. Stainless currently doesn't support mixing operands of
   /// different signedness, however, so we reject such cases.
@@ -174,36 +232,17 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
   ) -> st::Expr<'l> {
     let f = self.factory();
     let bail = |bxtor: &mut BodyExtractor<'_, 'l, 'tcx>, msg| bxtor.unsupported_expr(span, msg);
-    match (&arg1_ty.kind, &arg2_ty.kind) {
-      (kind1, kind2) if kind1 == kind2 => {} // No need to adapt anything,
-      (TyKind::Int(int_ty1), TyKind::Int(int_ty2)) => {
-        match (int_ty1.bit_width(), int_ty2.bit_width()) {
-          (Some(width1), Some(width2)) => {
-            if width1 > width2 {
-              arg2 = f.BVWideningCast(arg2, f.BVType(true, width1 as i32)).into();
-            } else {
-              return bail(self, "Adapting lhs shift argument would change result type");
-            }
-          }
-          _ => {
-            return bail(self, "Cannot adapt shift arguments of unknown bit widths");
-          }
-        }
-      }
-      (TyKind::Uint(uint_ty1), TyKind::Uint(uint_ty2)) => {
-        match (uint_ty1.bit_width(), uint_ty2.bit_width()) {
-          (Some(width1), Some(width2)) => {
-            if width1 > width2 {
-              arg2 = f
-                .BVWideningCast(arg2, f.BVType(false, width1 as i32))
-                .into();
-            } else {
-              return bail(self, "Adapting lhs shift argument would change result type");
-            }
-          }
-          _ => {
-            return bail(self, "Cannot adapt shift arguments of unknown bit widths");
-          }
+    match (
+      self.base.bv_type_params(arg1_ty),
+      self.base.bv_type_params(arg2_ty),
+    ) {
+      (Some((signed1, width1)), Some((signed2, width2))) if signed1 == signed2 => {
+        if width1 > width2 {
+          arg2 = f
+            .BVWideningCast(arg2, f.BVType(signed1, width1 as i32))
+            .into();
+        } else if width1 < width2 {
+          return bail(self, "Adapting lhs shift argument would change result type");
         }
       }
       _ => {
@@ -222,6 +261,109 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     }
   }
 
+  /// Extract an `as` cast to an integer type. Like in Rust, casts between integers first change
+  /// the width, extending the sign of signed sources, and then reinterpret the resulting bits.
+  fn extract_cast(
+    &mut self,
+    source: ExprRef<'tcx>,
+    target_ty: Ty<'tcx>,
+    span: Span,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    let source = self.mirror(source);
+    let source_ty = source.ty;
+    let (signed, width) = match self.base.bv_type_params(target_ty) {
+      Some(params) => params,
+      None => return self.unsupported_expr(span, format!("Cannot extract cast to {}", target_ty)),
+    };
+
+    match source_ty.kind {
+      TyKind::Bool => {
+        let arg = self.extract_expr(source);
+        let one = Literal::bv(signed, width, 1).as_st_literal(f);
+        let zero = Literal::bv(signed, width, 0).as_st_literal(f);
+        f.IfExpr(arg, one, zero).into()
+      }
+      TyKind::Adt(adt_def, _) if adt_def.is_payloadfree() => {
+        self.extract_discriminant_cast(source, adt_def, signed, width)
+      }
+      _ => match self.base.bv_type_params(source_ty) {
+        Some((source_signed, source_width)) => {
+          let arg = self.extract_expr(source);
+          let resized_tpe = f.BVType(source_signed, width as i32).into();
+          let arg = if source_width < width {
+            f.BVWideningCast(arg, resized_tpe).into()
+          } else if source_width > width {
+            f.BVNarrowingCast(arg, resized_tpe).into()
+          } else {
+            arg
+          };
+          if source_signed != signed {
+            self.extract_sign_change(arg, signed, width)
+          } else {
+            arg
+          }
+        }
+        None => self.unsupported_expr(
+          span,
+          format!("Cannot extract cast from {} to {}", source_ty, target_ty),
+        ),
+      },
+    }
+  }
+
+  /// Reinterpret a bitvector as one of the same width but the given signedness. Stainless has no
+  /// primitive for this, so we reassemble the value bit by bit. The argument is bound to a
+  /// variable first, rather than evaluated once per bit.
+  fn extract_sign_change(&mut self, arg: st::Expr<'l>, signed: bool, width: u64) -> st::Expr<'l> {
+    let f = self.factory();
+    let arg_var = self.fresh_var("bits", f.BVType(!signed, width as i32).into());
+    let bit = |signed, i| Literal::bv(signed, width, 1 << i).as_st_literal(f);
+    let zero = |signed| Literal::bv(signed, width, 0).as_st_literal(f);
+    let mut bits = (0..width).map(|i| -> st::Expr<'l> {
+      let masked = f.BVAnd(arg_var.into(), bit(!signed, i)).into();
+      let is_set = f.Not(f.Equals(masked, zero(!signed)).into()).into();
+      f.IfExpr(is_set, bit(signed, i), zero(signed)).into()
+    });
+    let lowest = bits.next().unwrap();
+    let value = bits.fold(lowest, |value, bit| f.BVOr(value, bit).into());
+    f.Let(f.ValDef(arg_var), arg, value).into()
+  }
+
+  /// Extract the discriminant of a C-like enum value by matching on its variants.
+  fn extract_discriminant_cast(
+    &mut self,
+    source: Expr<'tcx>,
+    adt_def: &'tcx AdtDef,
+    signed: bool,
+    width: u64,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    let tcx = self.tcx();
+    let scrutinee = self.extract_expr(source);
+    let sort = self.base.extract_adt(adt_def.did);
+
+    // Discriminants are given as the bits of the enum's representation type
+    let repr_ty = adt_def.repr.discr_type();
+    let repr_width = Integer::from_attr(&tcx, repr_ty).size().bits();
+    let cases = adt_def
+      .discriminants(tcx)
+      .zip(sort.constructors.iter())
+      .map(|((_, discr), cons)| {
+        let value = if repr_ty.is_signed() {
+          let shift = 128 - repr_width;
+          (((discr.val << shift) as i128) >> shift) as u128
+        } else {
+          discr.val
+        };
+        let pattern = f.ADTPattern(None, cons.id, vec![], vec![]).into();
+        let rhs = Literal::bv(signed, width, value).as_st_literal(f);
+        &*f.MatchCase(pattern, None, rhs)
+      })
+      .collect();
+    f.MatchExpr(scrutinee, cases).into()
+  }
+
   fn extract_logical_op(&mut self, expr: Expr<'tcx>) -> st::Expr<'l> {
     let f = self.factory();
     if let ExprKind::LogicalOp {
@@ -242,6 +384,40 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     }
   }
 
+  fn extract_assignment(&mut self, expr: Expr<'tcx>) -> st::Expr<'l> {
+    if let ExprKind::Assign { lhs, rhs } = expr.kind {
+      let lhs = self.mirror(lhs);
+      match self.try_extract_assignee(lhs) {
+        Ok(assignee) => {
+          let value = self.extract_expr_ref(rhs);
+          assignee.assign(self.factory(), value)
+        }
+        Err(reason) => self.unsupported_expr(expr.span, reason),
+      }
+    } else {
+      unreachable!()
+    }
+  }
+
+  /// Compound assignments such as `x += y` are extracted as `x = x + y`.
+  fn extract_assignment_op(&mut self, expr: Expr<'tcx>) -> st::Expr<'l> {
+    if let ExprKind::AssignOp { op, lhs, rhs } = expr.kind {
+      let (lhs, rhs) = (self.mirror(lhs), self.mirror(rhs));
+      let (lhs_ty, rhs_ty) = (lhs.ty, rhs.ty);
+      match self.try_extract_assignee(lhs) {
+        Ok(assignee) => {
+          let f = self.factory();
+          let rhs = self.extract_expr(rhs);
+          let value = self.extract_binary_op(op, assignee.value(f), rhs, lhs_ty, rhs_ty, expr.span);
+          assignee.assign(f, value)
+        }
+        Err(reason) => self.unsupported_expr(expr.span, reason),
+      }
+    } else {
+      unreachable!()
+    }
+  }
+
   fn extract_tuple(&mut self, expr: Expr<'tcx>) -> st::Expr<'l> {
     let f = self.factory();
     if let ExprKind::Tuple { fields } = expr.kind {
@@ -266,6 +442,7 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
           let lhs = self.extract_expr(lhs);
           f.TupleSelect(lhs, (index as i32) + 1).into()
         }
+        TyKind::Adt(adt_def, _) if self.base.is_wrapping(adt_def) => self.extract_expr(lhs),
         TyKind::Adt(adt_def, _) => {
           let sort = self.base.extract_adt(adt_def.did);
           assert_eq!(sort.constructors.len(), 1);
@@ -291,6 +468,13 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     span: Span,
   ) -> st::Expr<'l> {
     if let TyKind::FnDef(def_id, substs_ref) = ty.kind {
+      // Calls of closures and `Fn*` parameters are applications of lambdas
+      if let Some(trait_def_id) = self.tcx().trait_of_item(def_id) {
+        if self.base.is_fn_like_trait(trait_def_id) {
+          return self.extract_application(args, span);
+        }
+      }
+
       // If the call is a std item, extract it specially
       if let Some(std_item) = self.base.std_items.def_to_item_opt(def_id) {
         match std_item {
@@ -301,14 +485,123 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
           SetAddFn | SetDifferenceFn | SetIntersectionFn | SetUnionFn | SubsetOfFn => {
             return self.extract_set_op(std_item, args, span)
           }
+
+          InvariantFn => {
+            return self.unsupported_expr(
+              span,
+              "Loop invariants may only appear at the beginning of a loop body",
+            )
+          }
+          OldFn => return self.extract_old(args, span),
+          AssumeFn => {
+            let f = self.factory();
+            let fact = self.extract_expr_ref(args[0].clone());
+            return f.Assume(fact, f.UnitLiteral().into()).into();
+          }
+          ForallFn | ExistsFn => return self.extract_quantifier(std_item, args, span),
+          _ if std_item.is_model_fn() => {
+            return self.extract_model_call(std_item, substs_ref, args, span)
+          }
           _ => (),
         };
       }
+
+      if let Some(expr) = self.try_extract_bigint_call(def_id, substs_ref, args, span) {
+        return expr;
+      }
+      if let Some(expr) = self.try_extract_equality_call(def_id, substs_ref, args, span) {
+        return expr;
+      }
+      if let Some(expr) = self.try_extract_overflow_call(def_id, substs_ref, args, span) {
+        return expr;
+      }
     }
     // Otherwise, extract a normal call
     self.extract_call(ty, args, span)
   }
 
+  /// Extract a closure as a lambda. Captured variables simply remain free in the lambda's body,
+  /// so we only support closures that don't mutate their captures.
+  fn extract_closure(&mut self, closure_ty: Ty<'tcx>, span: Span) -> st::Expr<'l> {
+    match self.extract_closure_parts(closure_ty, span) {
+      Ok((params, body)) => self.factory().Lambda(params, body).into(),
+      Err(msg) => self.unsupported_expr(span, msg),
+    }
+  }
+
+  /// The parameters and the body of a closure that doesn't mutate its captures and only binds
+  /// its parameters to plain variables
+  fn extract_closure_parts(
+    &mut self,
+    closure_ty: Ty<'tcx>,
+    span: Span,
+  ) -> Result<(Params<'l>, st::Expr<'l>), &'static str> {
+    let f = self.factory();
+    let tcx = self.tcx();
+    let (def_id, substs) = match closure_ty.kind {
+      TyKind::Closure(def_id, substs) => (def_id, substs),
+      _ => unexpected(span, "Expected a closure"),
+    };
+    if substs.as_closure().kind() == ty::ClosureKind::FnMut {
+      return Err("Cannot extract closures mutating their captures");
+    }
+
+    let hir_id = tcx.hir().as_local_hir_id(def_id.expect_local());
+    let body = tcx.hir().body(tcx.hir().body_owned_by(hir_id));
+    let destructures = |param: &hir::Param| match param.pat.kind {
+      hir::PatKind::Binding(_, _, _, None) => false,
+      _ => true,
+    };
+    if body.params.iter().any(destructures) {
+      return Err("Cannot extract closures destructuring their parameters");
+    }
+
+    self.populate_closure_def_context(body);
+    let params = body
+      .params
+      .iter()
+      .map(|param| &*f.ValDef(self.fetch_var(param.pat.hir_id)))
+      .collect();
+    let body_expr = self.hcx.mirror(&body.value);
+    Ok((params, self.extract_expr(body_expr)))
+  }
+
+  /// Extract `forall(|x: T| p(x))` as a universal quantification over the closure's parameters,
+  /// and `exists` as its dual.
+  fn extract_quantifier(
+    &mut self,
+    std_item: StdItem,
+    args: &[ExprRef<'tcx>],
+    span: Span,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    let arg = self.mirror(args[0].clone());
+    let arg = self.strip_scopes(arg);
+    let (params, body) = match arg.kind {
+      ExprKind::Closure { .. } => match self.extract_closure_parts(arg.ty, arg.span) {
+        Ok(parts) => parts,
+        Err(msg) => return self.unsupported_expr(span, msg),
+      },
+      _ => return self.unsupported_expr(span, "Quantifiers must be given a closure literal"),
+    };
+    match std_item {
+      ForallFn => f.Forall(params, body).into(),
+      _ => f.Not(f.Forall(params, f.Not(body).into()).into()).into(),
+    }
+  }
+
+  /// Extract a call through one of the `Fn*` traits. Its arguments are passed as a tuple.
+  fn extract_application(&mut self, args: &[ExprRef<'tcx>], span: Span) -> st::Expr<'l> {
+    let fun = self.extract_expr_ref(args[0].clone());
+    match self.mirror(args[1].clone()).kind {
+      ExprKind::Tuple { fields } => {
+        let args = self.extract_expr_refs(fields);
+        self.factory().Application(fun, args).into()
+      }
+      _ => self.unsupported_expr(span, "Cannot extract call with untupled arguments"),
+    }
+  }
+
   fn extract_set_op(
     &mut self,
     std_item: StdItem,
@@ -342,9 +635,52 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     self.factory().FiniteSet(args, ty).into()
   }
 
+  /// Extract `old(e)`, the value of `e` when the function was entered. Stainless only takes the
+  /// old value of variables, so we push `Old` down to the variable `e` is a path of.
+  fn extract_old(&mut self, args: &[ExprRef<'tcx>], span: Span) -> st::Expr<'l> {
+    if !self.in_postcondition {
+      return self.unsupported_expr(span, "old(..) may only appear in postconditions");
+    }
+    let arg = self.extract_expr_ref(args[0].clone());
+    match self.try_old_path(arg) {
+      Some(expr) => expr,
+      None => self.unsupported_expr(
+        span,
+        "Can only take the old value of variables and their fields",
+      ),
+    }
+  }
+
+  fn try_old_path(&mut self, expr: st::Expr<'l>) -> Option<st::Expr<'l>> {
+    let f = self.factory();
+    match expr {
+      st::Expr::Variable(var) => Some(f.Old(var.into()).into()),
+      st::Expr::ADTSelector(&st::ADTSelector { adt, selector }) => self
+        .try_old_path(adt)
+        .map(|adt| f.ADTSelector(adt, selector).into()),
+      st::Expr::TupleSelect(&st::TupleSelect { tuple, index }) => self
+        .try_old_path(tuple)
+        .map(|tuple| f.TupleSelect(tuple, index).into()),
+      _ => None,
+    }
+  }
+
+  /// Extract a call to a statically known function.
+  ///
+  /// Method calls reach this point in the same shape as calls to free functions: HAIR passes the
+  /// receiver (after auto-ref and auto-deref) as the first argument, so `foo.get()`,
+  /// `Foo::get(&foo)` and associated functions like `Foo::new()` are all extracted as
+  /// `FunctionInvocation`s of the corresponding impl function.
   fn extract_call(&mut self, ty: Ty<'tcx>, args: &Vec<ExprRef<'tcx>>, span: Span) -> st::Expr<'l> {
     // Normal function call
     if let TyKind::FnDef(def_id, substs_ref) = ty.kind {
+      // Calls to methods of user-defined traits are dispatched on the trait's evidence
+      if let Some(trait_def_id) = self.tcx().trait_of_item(def_id) {
+        if self.base.is_user_trait(trait_def_id) {
+          return self.extract_trait_method_call(trait_def_id, def_id, substs_ref, args, span);
+        }
+      }
+
       let fd_id = self.base.extract_fn_ref(def_id);
 
       // Special case for Box::new, erase it and return the argument directly.
@@ -355,7 +691,8 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
       }
 
       let arg_tps = self.extract_arg_types(substs_ref, span);
-      let args = self.extract_expr_refs(args.to_vec());
+      let mut args = self.extract_expr_refs(args.to_vec());
+      args.extend(self.extract_evidence_args(def_id, substs_ref, span));
       self
         .factory()
         .FunctionInvocation(fd_id, arg_tps, args)
@@ -365,7 +702,11 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     }
   }
 
-  fn extract_arg_types(&mut self, substs: SubstsRef<'tcx>, span: Span) -> Vec<st::Type<'l>> {
+  pub(super) fn extract_arg_types(
+    &mut self,
+    substs: SubstsRef<'tcx>,
+    span: Span,
+  ) -> Vec<st::Type<'l>> {
     // Remove closure type parameters (they were already replaced by FunctionTypes)
     let arg_tys = substs.types().filter(|ty| match ty.kind {
       TyKind::Closure(..) => false,
@@ -382,7 +723,7 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     let arg = args.pop().unwrap();
     // FIXME: It seems that for expressions encoding panics, `expr.ty` always gives us the
     // `never` type, rather than the expected one. We currently just use the Unit type here,
-    // because this is correct for panics resulting from asserts, but we should really recover,
+    // because this is correct for panics in statement position, but we should really recover,
     // or -- if necessary -- infer the correct type instead. (Stainless will reject any ill-typed
     // programs.)
     let tpe = f.UnitType().into();
@@ -397,88 +738,294 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     }
   }
 
-  /*
-  // Expressions for which `e.clone()` can be translated simply as `e`.
-  // This is sound, in particular, for types for which we don't extract any
-  // mutating operations.
-  fn can_treat_clone_as_identity(&mut self, expr: &'tcx hir::Expr<'tcx>) -> bool {
-    let expr_ty = self.tables.node_type(expr.hir_id);
-    match expr_ty.kind {
-      TyKind::Adt(adt_def, _) => self.base.is_bigint(adt_def),
-      _ => false,
+  /// Extract the expansion of an assertion macro as a Stainless assertion carrying the message
+  /// the macro would panic with. The `debug_` variants are checked just like the others.
+  fn extract_assertion(&mut self, expr: Expr<'tcx>) -> st::Expr<'l> {
+    let f = self.factory();
+    let (scrutinee, arms) = match expr.kind {
+      ExprKind::Match { scrutinee, arms } => (scrutinee, arms),
+      _ => unreachable!(),
+    };
+
+    match self.looks_like_if(scrutinee.clone(), &arms) {
+      // `debug_assert!(..)` is `if cfg!(debug_assertions) { assert!(..); }`
+      Some(false) if is_debug_assertion(expr.span) => self.extract_expr_ref(arms[0].body.clone()),
+
+      // `assert!(cond)` is `if !cond { panic!(..) }`
+      Some(false) => {
+        let message = self.panic_message(arms[0].body.clone());
+        let cond = self.mirror(scrutinee);
+        let cond = self.strip_uses(cond);
+        let pred = match cond.kind {
+          ExprKind::Unary { op: UnOp::Not, arg } => self.extract_expr_ref(arg),
+          _ => {
+            let cond = self.extract_expr(cond);
+            f.Not(cond).into()
+          }
+        };
+        f.Assert(pred, message, f.UnitLiteral().into()).into()
+      }
+
+      // `assert_eq!(a, b)` and `assert_ne!(a, b)` bind both sides in a match and assert within
+      // it. We move the match into the predicate, so that the assertion ends up at the top.
+      None if arms.len() == 1 => match self.extract_match(scrutinee, arms) {
+        st::Expr::MatchExpr(st::MatchExpr { scrutinee, cases }) => match cases[0].rhs {
+          st::Expr::Assert(st::Assert {
+            pred,
+            error,
+            body: st::Expr::UnitLiteral(_),
+          }) => {
+            let case = f.MatchCase(cases[0].pattern, cases[0].optGuard, *pred);
+            let pred = f.MatchExpr(*scrutinee, vec![case]).into();
+            f.Assert(pred, error.clone(), f.UnitLiteral().into()).into()
+          }
+          _ => self.unsupported_expr(expr.span, "Cannot extract this form of assertion"),
+        },
+        _ => self.unsupported_expr(expr.span, "Cannot extract this form of assertion"),
+      },
+
+      _ => self.unsupported_expr(expr.span, "Cannot extract this form of assertion"),
     }
   }
 
-  fn extract_conversion_into(
-    &mut self,
-    outer: &'tcx hir::Expr<'tcx>,
-    inner: &'tcx hir::Expr<'tcx>,
-  ) -> st::Expr<'l> {
-    let from_ty = self.tables.node_type(inner.hir_id);
-    let to_ty = self.tables.node_type(outer.hir_id);
-    match (&from_ty.kind, &to_ty.kind) {
-      (TyKind::Int(_), TyKind::Adt(adt_def, _)) if self.base.is_bigint(adt_def) => self
-        .try_extract_bigint_lit(inner)
-        .unwrap_or_else(|reason| self.unsupported_expr(inner, reason)),
-      _ => self.unsupported_expr(
-        outer,
-        format!("Cannot extract conversion from {} to {}", from_ty, to_ty),
-      ),
+  /// The message of a panic that ends the given expression. Formatted messages are given as
+  /// their format string.
+  fn panic_message(&mut self, expr: ExprRef<'tcx>) -> Option<String> {
+    let expr = self.mirror(expr);
+    let expr = self.strip_uses(expr);
+    match expr.kind {
+      ExprKind::Block { body: ast_block } => {
+        let Block {
+          mut stmts, expr, ..
+        } = self.mirror(ast_block);
+        match (stmts.pop(), expr) {
+          (None, Some(expr)) => self.panic_message(expr),
+          (Some(stmt), None) if stmts.is_empty() => match self.mirror(stmt).kind {
+            StmtKind::Expr { expr, .. } => self.panic_message(expr),
+            _ => None,
+          },
+          _ => None,
+        }
+      }
+      ExprKind::NeverToAny { source } => self.panic_message(source),
+      ExprKind::Call { ty, args, .. } => match ty.kind {
+        TyKind::FnDef(def_id, _) => match self.base.std_items.def_to_item_opt(def_id) {
+          Some(BeginPanicFn) => self.string_literal(args[0].clone()),
+          // `begin_panic_fmt(&Arguments::new_v1(&[pieces..], &[args..]))`
+          Some(BeginPanicFmtFn) => {
+            let arguments = self.mirror(args[0].clone());
+            let arguments = match self.strip_uses(arguments).kind {
+              ExprKind::Borrow { arg, .. } => self.mirror(arg),
+              _ => return None,
+            };
+            let pieces = match self.strip_uses(arguments).kind {
+              ExprKind::Call { args, .. } if !args.is_empty() => self.mirror(args[0].clone()),
+              _ => return None,
+            };
+            let pieces = match self.strip_uses(pieces).kind {
+              ExprKind::Borrow { arg, .. } => self.mirror(arg),
+              _ => return None,
+            };
+            match self.strip_uses(pieces).kind {
+              ExprKind::Array { fields } => fields
+                .into_iter()
+                .map(|piece| self.string_literal(piece))
+                .collect::<Option<Vec<_>>>()
+                .map(|pieces| pieces.join("{}")),
+              _ => None,
+            }
+          }
+          _ => None,
+        },
+        _ => None,
+      },
+      _ => None,
+    }
+  }
+
+  fn string_literal(&mut self, expr: ExprRef<'tcx>) -> Option<String> {
+    let expr = self.mirror(expr);
+    match self.strip_uses(expr).kind {
+      ExprKind::Literal { literal, .. } => match self.literal(literal) {
+        Ok(Literal::String(value)) => Some(value),
+        _ => None,
+      },
+      _ => None,
     }
   }
 
-  fn try_extract_bigint_lit(&mut self, expr: &'tcx hir::Expr<'tcx>) -> Result<'l> {
-    use ast::LitKind;
+  /// Operators on `BigInt`s are calls to the methods of the corresponding traits, which we
+  /// extract as Stainless integer arithmetic. Conversions from integer literals become integer
+  /// literals, and clones are erased.
+  fn try_extract_bigint_call(
+    &mut self,
+    def_id: DefId,
+    substs: SubstsRef<'tcx>,
+    args: &[ExprRef<'tcx>],
+    span: Span,
+  ) -> Option<st::Expr<'l>> {
     let f = self.factory();
-    if let ExprKind::Lit(ref lit) = expr.kind {
-      match lit.node {
-        LitKind::Int(value, _) => {
-          let node_ty = self.tables.node_type(expr.hir_id);
-          match node_ty.kind {
-            ty::Int(_) => Ok(f.IntegerLiteral((value as i128).into()).into()),
-            _ => Err("Cannot extract BigInt from non-signed-int literal"),
-          }
-        }
-        _ => Err("Cannot extract BigInt from non-integral literal kind"),
+    let tcx = self.tcx();
+    let trait_def_id = tcx.trait_of_item(def_id)?;
+
+    // `From::from` is parameterized by its result type, `Into::into` by its argument type
+    let trait_item = self.base.std_items.def_to_item_opt(trait_def_id);
+    let target_ty = match trait_item {
+      Some(FromTrait) => Some(substs.type_at(0)),
+      Some(IntoTrait) => Some(substs.type_at(1)),
+      _ => None,
+    };
+    if let Some(target_ty) = target_ty {
+      if !self.base.is_bigint_type(target_ty) {
+        return None;
       }
-    } else {
-      Err("Can only extract BigInt from integer literals")
+      return Some(self.extract_bigint_operand(args[0].clone()));
     }
+
+    if !self.base.is_bigint_type(substs.type_at(0)) {
+      return None;
+    }
+    let trait_item = trait_item?;
+    let method_name = tcx.item_name(def_id);
+    if let (CloneTrait, "clone") = (trait_item, &*method_name.as_str()) {
+      return Some(self.extract_expr_ref(args[0].clone()));
+    }
+
+    let args: Vec<st::Expr<'l>> = args
+      .iter()
+      .map(|arg| self.extract_bigint_operand(arg.clone()))
+      .collect();
+    let expr = match (trait_item, &*method_name.as_str()) {
+      (NegTrait, "neg") => f.UMinus(args[0]).into(),
+      (AddTrait, "add") => f.Plus(args[0], args[1]).into(),
+      (SubTrait, "sub") => f.Minus(args[0], args[1]).into(),
+      (MulTrait, "mul") => f.Times(args[0], args[1]).into(),
+      (DivTrait, "div") => f.Division(args[0], args[1]).into(),
+      (RemTrait, "rem") => f.Remainder(args[0], args[1]).into(),
+      (PartialEqTrait, "eq") => f.Equals(args[0], args[1]).into(),
+      (PartialEqTrait, "ne") => f.Not(f.Equals(args[0], args[1]).into()).into(),
+      (PartialOrdTrait, "lt") => f.LessThan(args[0], args[1]).into(),
+      (PartialOrdTrait, "le") => f.LessEquals(args[0], args[1]).into(),
+      (PartialOrdTrait, "gt") => f.GreaterThan(args[0], args[1]).into(),
+      (PartialOrdTrait, "ge") => f.GreaterEquals(args[0], args[1]).into(),
+      _ => {
+        let msg = format!("Cannot extract BigInt operation {}", method_name);
+        self.unsupported_expr(span, msg)
+      }
+    };
+    Some(expr)
   }
 
-  fn try_extract_bigint_expr(&mut self, expr: Expr<'tcx>) -> Result<'l> {
-    self.try_extract_bigint_lit(expr).or_else(|_| {
-      let expr_ty = self.tables.node_type(expr.hir_id);
-      if self.base.is_bigint_type(expr_ty) {
-        Ok(self.extract_expr(expr))
-      } else {
-        Err("Not a BigInt-convertible expr")
+  /// Comparisons of values whose `PartialEq` is derived are extracted as Stainless' structural
+  /// equality. Other comparisons are extracted as calls to the `eq` of the user's impl, where
+  /// `ne` defaults to the negation of `eq`.
+  fn try_extract_equality_call(
+    &mut self,
+    def_id: DefId,
+    substs: SubstsRef<'tcx>,
+    args: &[ExprRef<'tcx>],
+    span: Span,
+  ) -> Option<st::Expr<'l>> {
+    let f = self.factory();
+    let tcx = self.tcx();
+    let trait_def_id = tcx.trait_of_item(def_id)?;
+    if self.base.std_items.def_to_item_opt(trait_def_id) != Some(PartialEqTrait) {
+      return None;
+    }
+    let is_ne = match &*tcx.item_name(def_id).as_str() {
+      "eq" => false,
+      "ne" => true,
+      _ => return None,
+    };
+
+    let (lhs_ty, rhs_ty) = (substs.type_at(0), substs.type_at(1));
+    if tcx.erase_regions(&lhs_ty) == tcx.erase_regions(&rhs_ty)
+      && self.base.has_structural_eq(self.txtcx.def_id, lhs_ty)
+    {
+      let args = self.extract_expr_refs(args.to_vec());
+      let equals = f.Equals(args[0], args[1]).into();
+      return Some(if is_ne { f.Not(equals).into() } else { equals });
+    }
+
+    // Type parameters may be instantiated with types of any equality
+    if tcx.erase_regions(&substs).needs_subst() {
+      let msg = format!(
+        "Cannot extract comparison of {} and {}, which depends on type parameters",
+        lhs_ty, rhs_ty
+      );
+      return Some(self.unsupported_expr(span, msg));
+    }
+
+    // Find the user's impl, which has to define `eq` and may define `ne`
+    let trait_ref = TraitRef::new(trait_def_id, substs);
+    let (impl_def_id, impl_substs) = match self.base.select_impl(self.txtcx.def_id, trait_ref) {
+      Some((impl_def_id, impl_substs)) if impl_def_id.is_local() => (impl_def_id, impl_substs),
+      _ => {
+        let msg = format!("Cannot extract comparison of {} and {}", lhs_ty, rhs_ty);
+        return Some(self.unsupported_expr(span, msg));
       }
-    })
+    };
+    let find_fn = |name: &str| {
+      tcx
+        .associated_items(impl_def_id)
+        .in_definition_order()
+        .find(|item| item.kind == ty::AssocKind::Fn && item.ident.name.as_str() == name)
+        .map(|item| item.def_id)
+    };
+    let (fn_def_id, negate) = match (find_fn("ne"), find_fn("eq")) {
+      (Some(ne_def_id), _) if is_ne => (ne_def_id, false),
+      (_, Some(eq_def_id)) => (eq_def_id, is_ne),
+      _ => unexpected(span, "PartialEq impl without eq"),
+    };
+
+    let fd_id = self.base.extract_fn_ref(fn_def_id);
+    let arg_tps = self.extract_arg_types(impl_substs, span);
+    let mut args = self.extract_expr_refs(args.to_vec());
+    args.extend(self.extract_evidence_args(fn_def_id, impl_substs, span));
+    let call = f.FunctionInvocation(fd_id, arg_tps, args).into();
+    Some(if negate { f.Not(call).into() } else { call })
   }
 
-  fn extract_method_call(&mut self, expr: &'tcx hir::Expr<'tcx>) -> st::Expr<'l> {
-    if let ExprKind::MethodCall(_path_seg, _, args) = expr.kind {
-      let def_path = self
-        .tables
-        .type_dependent_def(expr.hir_id)
-        .map(|(_, def_id)| def_id)
-        .map(|def_id| self.tcx().def_path_str(def_id))
-        .unwrap_or_else(|| "<unknown>".into());
-      let arg = &args[0];
-      // TODO: Fast check using `path_seg.ident.name == Symbol::intern("into")`?
-      match def_path.as_str() {
-        "std::convert::Into::into" => self.extract_conversion_into(expr, arg),
-        "std::clone::Clone::clone" if self.can_treat_clone_as_identity(expr) => {
-          self.extract_expr(arg)
+  /// Extract an operand of a `BigInt` operation, which is either a `BigInt` itself, or an
+  /// integer literal that is implicitly converted.
+  fn extract_bigint_operand(&mut self, arg: ExprRef<'tcx>) -> st::Expr<'l> {
+    let arg = self.mirror(arg);
+    let mut ty = arg.ty;
+    while let TyKind::Ref(_, inner_ty, _) = ty.kind {
+      ty = inner_ty;
+    }
+    if self.base.is_bigint_type(ty) {
+      return self.extract_expr(arg);
+    }
+
+    let span = arg.span;
+    match self.strip_scopes(arg).kind {
+      ExprKind::Literal { literal: konst, .. } => match self.literal(konst) {
+        // The literal holds the bits of its value, which we sign-extend
+        Ok(Literal::Int { value, size }) => {
+          let shift = 128 - size;
+          let value = (value << shift) >> shift;
+          self.factory().IntegerLiteral(value.into()).into()
         }
-        _ => self.unsupported_expr(expr, "Cannot extract general method calls"),
-      }
+        Ok(Literal::Uint { value, .. }) => self.factory().IntegerLiteral(value.into()).into(),
+        _ => self.unsupported_expr(span, "Can only convert integer literals to BigInt"),
+      },
+      _ => self.unsupported_expr(span, "Can only convert integer literals to BigInt"),
+    }
+  }
+
+  fn extract_unit_struct(&mut self, ty: Ty<'tcx>, span: Span) -> st::Expr<'l> {
+    if let TyKind::Adt(adt_def, substs) = ty.kind {
+      let sort = self.base.extract_adt(adt_def.did);
+      let arg_tps = self.extract_arg_types(substs, span);
+      self
+        .factory()
+        .ADT(sort.constructors[0].id, arg_tps, vec![])
+        .into()
     } else {
       unreachable!()
     }
   }
-  */
 
   fn extract_adt_construction(&mut self, expr: Expr<'tcx>) -> st::Expr<'l> {
     let f = self.factory();
@@ -493,6 +1040,8 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     {
       if base.is_some() {
         self.unsupported_expr(expr.span, "Cannot extract ADT constructions with bases")
+      } else if self.base.is_wrapping(adt_def) {
+        self.extract_expr_ref(fields.pop().unwrap().expr)
       } else {
         // TODO: Also consider type arguments
         let sort = self.base.extract_adt(adt_def.did);
@@ -528,6 +1077,387 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     f.IfExpr(cond, then, elze).into()
   }
 
+  /// Loops are extracted to Stainless `While` expressions.
+  ///
+  /// The desugaring of `while cond { .. }` is detected and directly turned back into a `While`.
+  /// A general `loop { .. }` is extracted as a `While` over a fresh flag, which is set by the
+  /// `break`s of the loop. We only support `break`s in tail position of the loop body, so that
+  /// setting the flag always ends the current iteration.
+  pub(super) fn extract_loop(
+    &mut self,
+    expr: Expr<'tcx>,
+    loop_scope: Option<region::Scope>,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    if let ExprKind::Loop { body } = expr.kind {
+      let body = self.mirror(body);
+      let Block {
+        mut stmts,
+        expr: final_expr,
+        ..
+      } = match self.strip_scopes(body).kind {
+        ExprKind::Block { body: ast_block } => self.mirror(ast_block),
+        _ => unexpected(expr.span, "Loop body is not a block"),
+      };
+
+      // Detect the desugaring of `while` loops
+      if let (true, Some(final_expr)) = (stmts.is_empty(), final_expr.clone()) {
+        let final_expr = self.mirror(final_expr);
+        if let ExprKind::Match { scrutinee, arms } = self.strip_scopes(final_expr).kind {
+          if self.looks_like_while(scrutinee.clone(), &arms, loop_scope) {
+            let cond = self.extract_expr_ref(scrutinee);
+            let then = self.mirror(arms[0].body.clone());
+            let (invariant, body) = match self.strip_scopes(then).kind {
+              ExprKind::Block { body: ast_block } => {
+                let mut block = self.mirror(ast_block);
+                let invariant = self.extract_loop_invariant(&mut block.stmts);
+                (invariant, self.extract_block(block))
+              }
+              _ => unexpected(expr.span, "Body of while loop is not a block"),
+            };
+            return f.While(cond, body, invariant).into();
+          }
+        }
+      }
+
+      // Otherwise, extract a general loop
+      let invariant = self.extract_loop_invariant(&mut stmts);
+      let flag = self.fresh_var("broken", f.BooleanType().into());
+      let body = self.extract_loop_tail_block(stmts, final_expr, loop_scope, flag);
+      let cond = f.Not(flag.into()).into();
+      let while_expr = f.While(cond, body, invariant).into();
+      f.LetVar(f.ValDef(flag), f.BooleanLiteral(false).into(), while_expr)
+        .into()
+    } else {
+      unreachable!()
+    }
+  }
+
+  /// Extract an expression in tail position of a loop body, where `break`s are permitted.
+  fn extract_loop_tail(
+    &mut self,
+    expr: Expr<'tcx>,
+    loop_scope: Option<region::Scope>,
+    flag: &'l st::Variable<'l>,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    match expr.kind {
+      ExprKind::Scope { value, .. }
+      | ExprKind::Use { source: value }
+      | ExprKind::NeverToAny { source: value } => {
+        let value = self.mirror(value);
+        self.extract_loop_tail(value, loop_scope, flag)
+      }
+
+      ExprKind::Break { label, value } if Some(label) == loop_scope => match value {
+        None => f.Assignment(flag, f.BooleanLiteral(true).into()).into(),
+        Some(_) => self.unsupported_expr(expr.span, "Cannot extract break with a value"),
+      },
+
+      ExprKind::Block { body: ast_block } => {
+        let Block { stmts, expr, .. } = self.mirror(ast_block);
+        self.extract_loop_tail_block(stmts, expr, loop_scope, flag)
+      }
+
+      // Assertions never break out of the loop
+      ExprKind::Match { .. } if assertion_macro(expr.span).is_some() => self.extract_expr(expr),
+
+      ExprKind::Match { scrutinee, arms } => match self.looks_like_if(scrutinee.clone(), &arms) {
+        Some(_) => {
+          let mut arms = arms;
+          let (elze, then) = (arms.pop().unwrap().body, arms.pop().unwrap().body);
+          let cond = self.extract_expr_ref(scrutinee);
+          let (then, elze) = (self.mirror(then), self.mirror(elze));
+          let then = self.extract_loop_tail(then, loop_scope, flag);
+          let elze = self.extract_loop_tail(elze, loop_scope, flag);
+          f.IfExpr(cond, then, elze).into()
+        }
+        None => {
+          let scrutinee = self.extract_expr_ref(scrutinee);
+          let cases = arms
+            .into_iter()
+            .map(|arm| {
+              let Arm {
+                pattern,
+                guard,
+                body,
+                ..
+              } = arm;
+              let pattern = self.extract_pattern(pattern, None);
+              let guard = guard.map(|Guard::If(expr)| self.extract_expr_ref(expr));
+              let body = self.mirror(body);
+              let body = self.extract_loop_tail(body, loop_scope, flag);
+              &*f.MatchCase(pattern, guard, body)
+            })
+            .collect();
+          f.MatchExpr(scrutinee, cases).into()
+        }
+      },
+
+      _ => self.extract_expr(expr),
+    }
+  }
+
+  /// Extract a block in tail position of a loop body. In the absence of a final expression,
+  /// the last statement of the block is in tail position.
+  fn extract_loop_tail_block(
+    &mut self,
+    mut stmts: Vec<StmtRef<'tcx>>,
+    final_expr: Option<ExprRef<'tcx>>,
+    loop_scope: Option<region::Scope>,
+    flag: &'l st::Variable<'l>,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    let final_expr = match final_expr {
+      Some(final_expr) => {
+        let final_expr = self.mirror(final_expr);
+        self.extract_loop_tail(final_expr, loop_scope, flag)
+      }
+      None => match stmts.pop().map(|stmt| self.mirror(stmt)) {
+        Some(Stmt {
+          kind: StmtKind::Expr { expr, .. },
+          ..
+        }) => {
+          let expr = self.mirror(expr);
+          let expr = self.extract_loop_tail(expr, loop_scope, flag);
+          self.keep_for_effects(f.UnitLiteral().into(), vec![expr])
+        }
+        Some(stmt) => {
+          stmts.push(StmtRef::Mirror(Box::new(stmt)));
+          f.UnitLiteral().into()
+        }
+        None => f.UnitLiteral().into(),
+      },
+    };
+    stmts.reverse();
+    self.extract_block_(&mut stmts, &mut vec![], final_expr)
+  }
+
+  /// For loops over integer ranges are extracted as `While` loops over a counter, along with
+  /// an automatic measure. Rustc desugars `for pat in head { body }` to
+  ///
+  /// ```ignore
+  /// match IntoIterator::into_iter(head) {
+  ///   mut iter => loop {
+  ///     let mut __next;
+  ///     match Iterator::next(&mut iter) {
+  ///       Some(val) => __next = val,
+  ///       None => break,
+  ///     };
+  ///     let pat = __next;
+  ///     body;
+  ///   }
+  /// }
+  /// ```
+  ///
+  /// If `pat` is an immutable binding, it is used as the counter, so that invariants can refer
+  /// to it. Otherwise we introduce a fresh counter.
+  pub(super) fn extract_for_loop(
+    &mut self,
+    scrutinee: ExprRef<'tcx>,
+    mut arms: Vec<Arm<'tcx>>,
+    span: Span,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+
+    // Find the bounds of the range
+    let into_iter_call = self.mirror(scrutinee);
+    let head = match self.strip_scopes(into_iter_call).kind {
+      ExprKind::Call { mut args, .. } => self.mirror(args.pop().unwrap()),
+      _ => unreachable!(),
+    };
+    let (start, end, is_inclusive) = match self.try_range_bounds(head) {
+      Ok(bounds) => bounds,
+      Err(reason) => return self.unsupported_expr(span, reason),
+    };
+    let elem_ty = start.ty;
+    if !self.base.is_bv_type(elem_ty) {
+      return self.unsupported_expr(span, "Can only extract for loops over bit-vector ranges");
+    }
+
+    // Take apart the desugared loop
+    let loop_expr = self.mirror(arms.pop().unwrap().body);
+    let loop_body = match self.strip_scopes(loop_expr).kind {
+      ExprKind::Loop { body } => self.mirror(body),
+      _ => unexpected(span, "Desugared for loop without a loop"),
+    };
+    let mut stmts = match self.strip_scopes(loop_body).kind {
+      ExprKind::Block { body: ast_block } => self.mirror(ast_block).stmts,
+      _ => unexpected(span, "Desugared for loop without a block"),
+    };
+    if stmts.len() != 4 {
+      unexpected(span, "Desugared for loop with unexpected statements");
+    }
+    let (body_stmt, pat_stmt) = (
+      self.mirror(stmts.pop().unwrap()),
+      self.mirror(stmts.pop().unwrap()),
+    );
+    let pattern = match pat_stmt.kind {
+      StmtKind::Let { pattern, .. } => pattern,
+      _ => unexpected(span, "Desugared for loop without a let of its pattern"),
+    };
+    let body = match body_stmt.kind {
+      StmtKind::Expr { expr, .. } => self.mirror(expr),
+      _ => unexpected(span, "Desugared for loop without a body"),
+    };
+    let mut block = match self.strip_scopes(body).kind {
+      ExprKind::Block { body: ast_block } => self.mirror(ast_block),
+      _ => unexpected(span, "Body of for loop is not a block"),
+    };
+    let invariant = self.extract_loop_invariant(&mut block.stmts);
+    let body = self.extract_block(block);
+
+    // Set up the counter and the bounds
+    let tpe = self.base.extract_ty(elem_ty, &self.txtcx, span);
+    let (counter, body) = match pattern.kind {
+      box PatKind::Wild => (self.fresh_var("counter", tpe), body),
+      box kind => match self.try_pattern_to_var(&kind, false) {
+        Ok((var, false)) => (var, body),
+        Ok((var, true)) => {
+          let counter = self.fresh_var("counter", tpe);
+          (
+            counter,
+            f.LetVar(f.ValDef(var), counter.into(), body).into(),
+          )
+        }
+        Err(reason) => {
+          return self.unsupported_expr(
+            pattern.span,
+            format!("Cannot extract pattern of for loop: {}", reason),
+          )
+        }
+      },
+    };
+    let end_var = self.fresh_var("end", tpe);
+    let start = self.extract_expr(start);
+    let end = self.extract_expr(end);
+
+    // Build the loop, incrementing the counter at the end of each iteration
+    let (signed, size) = match tpe {
+      st::Type::BVType(st::BVType { signed, size }) => (*signed, *size as u64),
+      _ => unreachable!(),
+    };
+    let literal = |value| Literal::bv(signed, size, value).as_st_literal(f);
+    let (zero, one) = (literal(0), literal(1));
+    let increment = f.Assignment(counter, f.Plus(counter.into(), one).into());
+
+    if !is_inclusive {
+      let body = f.Block(vec![body], increment.into()).into();
+      let cond = f.LessThan(counter.into(), end_var.into()).into();
+      let measure = f.Minus(end_var.into(), counter.into()).into();
+      let body = f.Decreases(measure, body).into();
+      let while_expr = f.While(cond, body, invariant).into();
+      let counter_expr = f.LetVar(f.ValDef(counter), start, while_expr).into();
+      return f.Let(f.ValDef(end_var), end, counter_expr).into();
+    }
+
+    // An inclusive range may end at the maximum value of its type, in which case the counter
+    // can't be incremented past the end. We then stop the loop with a flag instead.
+    let max = literal(if signed {
+      std::u128::MAX >> (129 - size)
+    } else {
+      std::u128::MAX
+    });
+    let done = self.fresh_var("done", f.BooleanType().into());
+    let at_max = f.Equals(counter.into(), max).into();
+    let step = f.IfExpr(
+      at_max,
+      f.Assignment(done, f.BooleanLiteral(true).into()).into(),
+      increment.into(),
+    );
+    let body = f.Block(vec![body], step.into()).into();
+
+    let in_range = f.LessEquals(counter.into(), end_var.into()).into();
+    let cond = f.And(vec![f.Not(done.into()).into(), in_range]).into();
+    // Lexicographically, first whether the loop goes on, then the distance to the end
+    let measure = f
+      .Tuple(vec![
+        f.IfExpr(cond, one, zero).into(),
+        f.IfExpr(
+          in_range,
+          f.Minus(end_var.into(), counter.into()).into(),
+          zero,
+        )
+        .into(),
+      ])
+      .into();
+    let body = f.Decreases(measure, body).into();
+    let done_at_max = f.Or(vec![f.Not(done.into()).into(), at_max]).into();
+    let invariant = Some(f.make_and(invariant.into_iter().chain(vec![done_at_max]).collect()));
+    let while_expr = f.While(cond, body, invariant).into();
+    let done_expr = f
+      .LetVar(f.ValDef(done), f.BooleanLiteral(false).into(), while_expr)
+      .into();
+    let counter_expr = f.LetVar(f.ValDef(counter), start, done_expr).into();
+    f.Let(f.ValDef(end_var), end, counter_expr).into()
+  }
+
+  /// Find the bounds of a range expression `start..end` or `start..=end`.
+  fn try_range_bounds(&mut self, head: Expr<'tcx>) -> Result<(Expr<'tcx>, Expr<'tcx>, bool)> {
+    match self.strip_scopes(head).kind {
+      ExprKind::Adt {
+        adt_def,
+        mut fields,
+        ..
+      } if self.base.std_items.def_to_item_opt(adt_def.did) == Some(RangeType) => {
+        fields.sort_by_key(|field| field.name.index());
+        let mut bounds: Vec<Expr<'tcx>> = fields
+          .into_iter()
+          .map(|field| self.mirror(field.expr))
+          .collect();
+        let end = bounds.pop().unwrap();
+        Ok((bounds.pop().unwrap(), end, false))
+      }
+      ExprKind::Call { ty, args, .. } if self.fn_std_item(ty) == Some(RangeInclusiveNewFn) => {
+        let mut bounds: Vec<Expr<'tcx>> = args.into_iter().map(|arg| self.mirror(arg)).collect();
+        let end = bounds.pop().unwrap();
+        Ok((bounds.pop().unwrap(), end, true))
+      }
+      _ => Err("Can only extract for loops over ranges"),
+    }
+  }
+
+  fn fn_std_item(&self, ty: Ty<'tcx>) -> Option<StdItem> {
+    match ty.kind {
+      TyKind::FnDef(def_id, _) => self.base.std_items.def_to_item_opt(def_id),
+      _ => None,
+    }
+  }
+
+  /// Remove the invariants stated at the beginning of a loop body and return their conjunction.
+  fn extract_loop_invariant(&mut self, stmts: &mut Vec<StmtRef<'tcx>>) -> Option<st::Expr<'l>> {
+    let mut invariants = vec![];
+    while !stmts.is_empty() {
+      let stmt = self.mirror(stmts.remove(0));
+      match self.try_extract_invariant(&stmt) {
+        Some(invariant) => invariants.push(invariant),
+        None => {
+          stmts.insert(0, StmtRef::Mirror(Box::new(stmt)));
+          break;
+        }
+      }
+    }
+    if invariants.is_empty() {
+      None
+    } else {
+      Some(self.factory().make_and(invariants))
+    }
+  }
+
+  fn try_extract_invariant(&mut self, stmt: &Stmt<'tcx>) -> Option<st::Expr<'l>> {
+    if let StmtKind::Expr { expr, .. } = &stmt.kind {
+      let expr = self.mirror(expr.clone());
+      if let ExprKind::Call { ty, args, .. } = self.strip_scopes(expr).kind {
+        if let TyKind::FnDef(def_id, _) = ty.kind {
+          if self.base.std_items.def_to_item_opt(def_id) == Some(InvariantFn) {
+            return Some(self.extract_expr_ref(args[0].clone()));
+          }
+        }
+      }
+    }
+    None
+  }
+
   fn extract_match(&mut self, scrutinee: ExprRef<'tcx>, arms: Vec<Arm<'tcx>>) -> st::Expr<'l> {
     let scrutinee = self.extract_expr_ref(scrutinee);
     let cases = arms.into_iter().map(|arm| self.extract_arm(arm)).collect();
@@ -547,7 +1477,7 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     self.factory().MatchCase(pattern, guard, body)
   }
 
-  fn extract_pattern(
+  pub(super) fn extract_pattern(
     &mut self,
     pattern: Pat<'tcx>,
     binder: Option<&'l st::ValDef<'l>>,
@@ -556,10 +1486,18 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     match pattern.kind {
       box PatKind::Wild => f.WildcardPattern(binder).into(),
 
+      box PatKind::Binding {
+        mutability: Mutability::Mut,
+        ..
+      } => self.unsupported_pattern(
+        pattern.span,
+        "Mutable bindings in patterns are not supported",
+      ),
+
       box kind @ PatKind::Binding { .. } => {
         assert!(binder.is_none());
         match self.try_pattern_to_var(&kind, true) {
-          Ok(binder) => {
+          Ok((binder, _)) => {
             let binder = f.ValDef(binder);
             match kind {
               PatKind::Binding {
@@ -617,7 +1555,7 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
         ),
       },
 
-      box PatKind::Constant { value: konst } => match Literal::try_from(konst) {
+      box PatKind::Constant { value: konst } => match self.literal(konst) {
         Ok(lit) => f.LiteralPattern(binder, lit.as_st_literal(f)).into(),
         _ => self.unsupported_pattern(pattern.span, "Unsupported kind of literal in pattern"),
       },
@@ -686,34 +1624,57 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
               .into()
           };
 
-          // FIXME: Detect desugared `let`s
-          let has_abnormal_source = false;
+          // Note that the `let`s resulting from desugaring `for` loops never reach this point,
+          // since we detect the entire loop in `extract_for_loop`.
           let var_result = self.try_pattern_to_var(&pattern.kind, false);
 
-          if has_abnormal_source {
-            // TODO: Support for loops
-            bail("Cannot extract let that resulted from desugaring")
-          } else if let Err(reason) = var_result {
+          if let Err(reason) = var_result {
             // TODO: Desugar complex patterns
             bail(format!("Cannot extract complex pattern in let: {}", reason).as_str())
           } else if initializer.is_none() {
             bail("Cannot extract let without initializer")
           } else {
-            let vd = f.ValDef(var_result.unwrap());
+            let (var, is_mutable) = var_result.unwrap();
+            let vd = f.ValDef(var);
             let init = self.extract_expr_ref(initializer.unwrap());
             let exprs = acc_exprs.clone();
             acc_exprs.clear();
             let body_expr = self.extract_block_(stmts, acc_exprs, final_expr);
-            let last_expr = f.Let(vd, init, body_expr).into();
+            let last_expr = if is_mutable {
+              f.LetVar(vd, init, body_expr).into()
+            } else {
+              f.Let(vd, init, body_expr).into()
+            };
             finish(exprs, last_expr)
           }
         }
 
-        StmtKind::Expr { expr, .. } => {
-          let expr = self.extract_expr_ref(expr);
-          acc_exprs.push(expr);
-          self.extract_block_(stmts, acc_exprs, final_expr)
-        }
+        StmtKind::Expr { expr, .. } => match self.extract_expr_ref(expr) {
+          // Assertions and assumptions hold in the rest of the block
+          st::Expr::Assert(st::Assert {
+            pred,
+            error,
+            body: st::Expr::UnitLiteral(_),
+          }) => {
+            let exprs = acc_exprs.clone();
+            acc_exprs.clear();
+            let body_expr = self.extract_block_(stmts, acc_exprs, final_expr);
+            finish(exprs, f.Assert(*pred, error.clone(), body_expr).into())
+          }
+          st::Expr::Assume(st::Assume {
+            pred,
+            body: st::Expr::UnitLiteral(_),
+          }) => {
+            let exprs = acc_exprs.clone();
+            acc_exprs.clear();
+            let body_expr = self.extract_block_(stmts, acc_exprs, final_expr);
+            finish(exprs, f.Assume(*pred, body_expr).into())
+          }
+          expr => {
+            acc_exprs.push(expr);
+            self.extract_block_(stmts, acc_exprs, final_expr)
+          }
+        },
       }
     } else {
       finish(acc_exprs.clone(), final_expr)
@@ -735,17 +1696,22 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
 
   /// Factory helpers
 
+  pub(super) fn fresh_var(&mut self, name: &str, tpe: st::Type<'l>) -> &'l st::Variable<'l> {
+    let id = self.base.fresh_id(name.into());
+    self.factory().Variable(id, tpe, vec![])
+  }
+
   fn keep_for_effects(&mut self, expr: st::Expr<'l>, exprs: Vec<st::Expr<'l>>) -> st::Expr<'l> {
     self.factory().Block(exprs, expr).into()
   }
 
   /// Various helpers
 
-  fn mirror<M: Mirror<'tcx>>(&mut self, m: M) -> M::Output {
+  pub(super) fn mirror<M: Mirror<'tcx>>(&mut self, m: M) -> M::Output {
     m.make_mirror(&mut self.hcx)
   }
 
-  fn strip_scopes(&mut self, expr: Expr<'tcx>) -> Expr<'tcx> {
+  pub(super) fn strip_scopes(&mut self, expr: Expr<'tcx>) -> Expr<'tcx> {
     match expr.kind {
       ExprKind::Scope { value, .. } => {
         let expr = self.mirror(value);
@@ -755,19 +1721,33 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     }
   }
 
+  /// Strip scopes as well as the uses introduced, e.g., around the conditions of `if`s.
+  fn strip_uses(&mut self, expr: Expr<'tcx>) -> Expr<'tcx> {
+    let expr = self.strip_scopes(expr);
+    match expr.kind {
+      ExprKind::Use { source } => {
+        let expr = self.mirror(source);
+        self.strip_uses(expr)
+      }
+      _ => expr,
+    }
+  }
+
   /// Try to detect whether the given match corresponds to an if expression.
   /// Returns None if it is not an if expression and Some(has_elze) otherwise.
-  fn looks_like_if(&mut self, scrutinee: ExprRef<'tcx>, arms: &[Arm<'tcx>]) -> Option<bool> {
+  pub(super) fn looks_like_if(
+    &mut self,
+    scrutinee: ExprRef<'tcx>,
+    arms: &[Arm<'tcx>],
+  ) -> Option<bool> {
     let cond = self.mirror(scrutinee);
     let is_if = arms.len() == 2
       && cond.ty.is_bool()
       && match (&arms[0].pattern.kind, &arms[1].pattern.kind) {
-        (box PatKind::Constant { value: konst }, box PatKind::Wild) => {
-          match Literal::try_from(*konst) {
-            Ok(Literal::Bool(true)) => true,
-            _ => false,
-          }
-        }
+        (box PatKind::Constant { value: konst }, box PatKind::Wild) => match self.literal(*konst) {
+          Ok(Literal::Bool(true)) => true,
+          _ => false,
+        },
         _ => false,
       };
 
@@ -786,31 +1766,69 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     }
   }
 
-  fn try_pattern_to_var(
+  /// Detect the match introduced by desugaring `while cond { .. }`, i.e.
+  /// `match cond { true => { .. }, _ => break }`.
+  fn looks_like_while(
+    &mut self,
+    scrutinee: ExprRef<'tcx>,
+    arms: &[Arm<'tcx>],
+    loop_scope: Option<region::Scope>,
+  ) -> bool {
+    let cond = self.mirror(scrutinee);
+    cond.ty.is_bool()
+      && arms.len() == 2
+      && match (&arms[0].pattern.kind, &arms[1].pattern.kind) {
+        (box PatKind::Constant { value: konst }, box PatKind::Wild) => match self.literal(*konst) {
+          Ok(Literal::Bool(true)) => {
+            let elze = self.mirror(arms[1].body.clone());
+            match self.strip_scopes(elze).kind {
+              ExprKind::Break { label, value: None } => Some(label) == loop_scope,
+              _ => false,
+            }
+          }
+          _ => false,
+        },
+        _ => false,
+      }
+  }
+
+  /// Detect the match introduced by desugaring `for` loops, i.e.
+  /// `match IntoIterator::into_iter(head) { mut iter => loop { .. } }`.
+  pub(super) fn looks_like_for_loop(
+    &mut self,
+    scrutinee: ExprRef<'tcx>,
+    arms: &[Arm<'tcx>],
+  ) -> bool {
+    let scrutinee = self.mirror(scrutinee);
+    arms.len() == 1
+      && scrutinee.span.is_desugaring(DesugaringKind::ForLoop)
+      && matches!(self.strip_scopes(scrutinee).kind, ExprKind::Call { .. })
+  }
+
+  /// Try to convert a pattern into a single variable binding. Also returns
+  /// whether the binding is mutable, i.e. whether it needs to become a `var`.
+  pub(super) fn try_pattern_to_var(
     &self,
     pat_kind: &PatKind<'tcx>,
     allow_subpattern: bool,
-  ) -> Result<&'l st::Variable<'l>> {
+  ) -> Result<(&'l st::Variable<'l>, bool)> {
     match pat_kind {
-      PatKind::Binding {
-        mutability: Mutability::Mut,
-        ..
-      } => Err("Mutable bindings are not supported"),
-
       PatKind::Binding {
         subpattern: Some(_),
         ..
       } if !allow_subpattern => Err("Subpatterns are not supported here"),
 
       PatKind::Binding {
-        mutability: Mutability::Not,
+        mutability,
         mode,
         var: hir_id,
         ..
-      } => match mode {
-        BindingMode::ByValue | BindingMode::ByRef(BorrowKind::Shared) => {
-          Ok(self.fetch_var(*hir_id))
+      } => match (mutability, mode) {
+        (Mutability::Not, BindingMode::ByValue)
+        | (Mutability::Not, BindingMode::ByRef(BorrowKind::Shared)) => {
+          Ok((self.fetch_var(*hir_id), false))
         }
+        (Mutability::Mut, BindingMode::ByValue) => Ok((self.fetch_var(*hir_id), true)),
         _ => Err("Binding mode not allowed"),
       },
 
@@ -826,7 +1844,82 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     }
   }
 
-  fn unsupported_expr<M: Into<String>>(&mut self, span: Span, msg: M) -> st::Expr<'l> {
+  /// Try to find the target of an assignment, which is either a mutable local variable, a
+  /// field of a struct, possibly behind a mutable reference, or an entire struct behind a
+  /// mutable reference.
+  fn try_extract_assignee(&mut self, lhs: Expr<'tcx>) -> Result<Assignee<'l>> {
+    let (lhs_ty, span) = (lhs.ty, lhs.span);
+    match self.strip_scopes(lhs).kind {
+      ExprKind::VarRef { id } => {
+        let span = self.tcx().hir().span(id);
+        match self.tables.extract_binding_mode(self.tcx().sess, id, span) {
+          Some(ty::BindByValue(Mutability::Mut)) => Ok(Assignee::Var(self.fetch_var(id))),
+          _ => Err("Cannot assign to an immutable binding"),
+        }
+      }
+
+      ExprKind::Field { lhs, name } => {
+        let lhs = self.mirror(lhs);
+        match lhs.ty.kind {
+          TyKind::Adt(adt_def, _) if adt_def.is_struct() => {
+            let sort = self.base.extract_adt(adt_def.did);
+            let field = sort.constructors[0].fields[name.index()].v;
+            let is_var = field
+              .flags
+              .iter()
+              .any(|flag| matches!(flag, st::Flag::IsVar(_)));
+            if is_var {
+              Ok(Assignee::Field(self.extract_expr(lhs), field.id))
+            } else {
+              Err("Cannot assign to a field that is not marked as `#[var]`")
+            }
+          }
+          TyKind::Tuple(_) => Err("Cannot assign to fields of tuples"),
+          _ => Err("Cannot assign to fields of enums"),
+        }
+      }
+
+      // Since references are erased, writing a struct through a mutable reference amounts to
+      // writing each of its fields.
+      ExprKind::Deref { arg } => {
+        let arg = self.mirror(arg);
+        let arg_ty = arg.ty;
+        match (&arg_ty.kind, &lhs_ty.kind) {
+          (TyKind::Ref(_, _, Mutability::Mut), TyKind::Adt(adt_def, _)) if adt_def.is_struct() => {
+            let sort = self.base.extract_adt(adt_def.did);
+            let fields: Vec<_> = sort.constructors[0]
+              .fields
+              .iter()
+              .map(|field| field.v)
+              .collect();
+            let all_var = fields.iter().all(|field| {
+              field
+                .flags
+                .iter()
+                .any(|flag| matches!(flag, st::Flag::IsVar(_)))
+            });
+            if all_var {
+              let tpe = self.base.extract_ty(lhs_ty, &self.txtcx, span);
+              let value_var = self.fresh_var("value", tpe);
+              let field_ids = fields.iter().map(|field| field.id).collect();
+              let obj = self.extract_expr(arg);
+              Ok(Assignee::Fields(obj, value_var, field_ids))
+            } else {
+              Err("Can only assign through mutable references to structs whose fields are all `#[var]`")
+            }
+          }
+          _ => Err("Can only assign through mutable references to structs"),
+        }
+      }
+      _ => Err("Can only assign to local variables and fields"),
+    }
+  }
+
+  fn literal(&self, konst: &'tcx ty::Const<'tcx>) -> std::result::Result<Literal, ()> {
+    Literal::from_const(konst, self.base.pointer_width)
+  }
+
+  pub(super) fn unsupported_expr<M: Into<String>>(&mut self, span: Span, msg: M) -> st::Expr<'l> {
     self.base.unsupported(span, msg);
     let f = self.factory();
     f.NoTree(f.Untyped().into()).into()
@@ -837,3 +1930,74 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     self.factory().WildcardPattern(None).into()
   }
 }
+
+/// The target of an assignment
+enum Assignee<'l> {
+  Var(&'l st::Variable<'l>),
+  Field(st::Expr<'l>, &'l st::SymbolIdentifier<'l>),
+  /// All fields of a struct, with a variable to hold the struct value being assigned
+  Fields(
+    st::Expr<'l>,
+    &'l st::Variable<'l>,
+    Vec<&'l st::SymbolIdentifier<'l>>,
+  ),
+}
+
+impl<'l> Assignee<'l> {
+  /// The current value of the assignee
+  fn value(&self, f: &'l st::Factory) -> st::Expr<'l> {
+    match *self {
+      Assignee::Var(var) => var.into(),
+      Assignee::Field(obj, field_id) => f.ADTSelector(obj, field_id).into(),
+      Assignee::Fields(obj, _, _) => obj,
+    }
+  }
+
+  fn assign(self, f: &'l st::Factory, value: st::Expr<'l>) -> st::Expr<'l> {
+    match self {
+      Assignee::Var(var) => f.Assignment(var, value).into(),
+      Assignee::Field(obj, field_id) => f.FieldAssignment(obj, field_id, value).into(),
+      Assignee::Fields(obj, value_var, field_ids) => {
+        let assignments = field_ids
+          .into_iter()
+          .map(|field_id| {
+            let field_value = f.ADTSelector(value_var.into(), field_id).into();
+            f.FieldAssignment(obj, field_id, field_value).into()
+          })
+          .collect();
+        let body = f.Block(assignments, f.UnitLiteral().into()).into();
+        f.Let(f.ValDef(value_var), value, body).into()
+      }
+    }
+  }
+}
+
+fn is_unit_struct(ty: Ty<'_>) -> bool {
+  match ty.kind {
+    TyKind::Adt(adt_def, _) => adt_def.is_struct() && adt_def.non_enum_variant().fields.is_empty(),
+    _ => false,
+  }
+}
+
+const ASSERTION_MACROS: &[&str] = &[
+  "assert",
+  "assert_eq",
+  "assert_ne",
+  "debug_assert",
+  "debug_assert_eq",
+  "debug_assert_ne",
+];
+
+/// The name of the assertion macro whose expansion the given span belongs to, if any
+fn assertion_macro(span: Span) -> Option<Symbol> {
+  match span.ctxt().outer_expn_data().kind {
+    ExpnKind::Macro(MacroKind::Bang, name) if ASSERTION_MACROS.contains(&&*name.as_str()) => {
+      Some(name)
+    }
+    _ => None,
+  }
+}
+
+fn is_debug_assertion(span: Span) -> bool {
+  assertion_macro(span).map_or(false, |name| name.as_str().starts_with("debug_"))
+}
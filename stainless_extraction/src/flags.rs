@@ -6,6 +6,7 @@ use rustc_ast::ast::{AttrKind, MacArgs};
 use rustc_ast::token::{Lit, LitKind, TokenKind};
 use rustc_ast::tokenstream::{TokenStream, TokenTree};
 use rustc_hir::HirId;
+use rustc_middle::ty::DefIdTree;
 use rustc_span::symbol::{kw, Symbol};
 
 use stainless_data::ast as st;
@@ -35,6 +36,10 @@ impl Flags {
     self.set.insert(flag);
   }
 
+  pub(super) fn contains(&self, flag: Flag) -> bool {
+    self.set.contains(&flag)
+  }
+
   pub(super) fn to_stainless<'l>(&self, f: &'l st::Factory) -> Vec<st::Flag<'l>> {
     self
       .set
@@ -81,6 +86,16 @@ lazy_static! {
 
 impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
   pub(super) fn extract_flags(&mut self, carrier_hid: HirId) -> (Flags, HashMap<Symbol, Flags>) {
+    self.parse_flags(carrier_hid, true)
+  }
+
+  /// Parse the stainless annotations on an item, optionally warning about malformed ones.
+  fn parse_flags(&self, carrier_hid: HirId, report: bool) -> (Flags, HashMap<Symbol, Flags>) {
+    let warn = |span: Span, msg: &str| {
+      if report {
+        self.tcx.sess.span_warn(span, msg);
+      }
+    };
     let attrs = self.tcx.hir().attrs(carrier_hid);
     let mut carrier_flags = Flags::new();
     let mut flags_by_symbol: HashMap<Symbol, Flags> = HashMap::new();
@@ -95,7 +110,7 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
               MacArgs::Empty => None,
               MacArgs::Delimited(_, _, tokens) => Some(tokens.clone()),
               _ => {
-                self.tcx.sess.span_warn(
+                warn(
                   attr.span(),
                   "Unsupported target specified on stainless annotation",
                 );
@@ -104,7 +119,7 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
             };
             (flag, arg_tokens)
           } else {
-            self.tcx.sess.span_warn(
+            warn(
               attr.span(),
               format!("Unknown stainless annotation: {}", name).as_str(),
             );
@@ -137,18 +152,12 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
                 ..
               }) => add_by_symbol(symbol),
               _ => {
-                self
-                  .tcx
-                  .sess
-                  .span_warn(tt.span(), "Unknown argument in stainless annotation");
+                warn(tt.span(), "Unknown argument in stainless annotation");
                 break;
               }
             },
             _ => {
-              self
-                .tcx
-                .sess
-                .span_warn(tt.span(), "Unknown argument in stainless annotation");
+              warn(tt.span(), "Unknown argument in stainless annotation");
               break;
             }
           }
@@ -161,15 +170,42 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     (carrier_flags, flags_by_symbol)
   }
 
+  /// Check whether an item carries the given flag, without reporting malformed annotations
+  pub(super) fn has_carrier_flag(&self, carrier_hid: HirId, flag: Flag) -> bool {
+    self.parse_flags(carrier_hid, false).0.contains(flag)
+  }
+
+  /// Extract the flags on a type parameter, which are given by annotating the item that
+  /// declares it, as in `#[mutable(T)]`.
+  pub(super) fn extract_tparam_flags(&self, param_def_id: DefId, name: Symbol) -> Option<Flags> {
+    let owner_def_id = self.tcx.parent(param_def_id)?.as_local()?;
+    let owner_hid = self.tcx.hir().as_local_hir_id(owner_def_id);
+    self.parse_flags(owner_hid, false).1.remove(&name)
+  }
+
   /// Warn about unused flags
   pub(super) fn report_unused_flags(
     &self,
     carrier_hid: HirId,
     flags_by_symbol: &HashMap<Symbol, Flags>,
   ) {
-    if !flags_by_symbol.is_empty() {
-      let names: String = flags_by_symbol
-        .keys()
+    // Flags on type parameters are extracted along with the latter
+    let carrier_def_id = self.tcx.hir().local_def_id(carrier_hid).to_def_id();
+    let tparam_names: HashSet<Symbol> = self
+      .tcx
+      .generics_of(carrier_def_id)
+      .params
+      .iter()
+      .map(|param| param.name)
+      .collect();
+    let unused: Vec<&Symbol> = flags_by_symbol
+      .keys()
+      .filter(|symbol| !tparam_names.contains(symbol))
+      .collect();
+
+    if !unused.is_empty() {
+      let names: String = unused
+        .iter()
         .map(|symbol| symbol.to_string())
         .collect::<Vec<String>>()
         .join(", ");
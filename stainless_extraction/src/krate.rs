@@ -2,13 +2,15 @@ use super::*;
 
 use rustc_hir::def_id::DefId;
 use rustc_hir::itemlikevisit::ItemLikeVisitor;
-use rustc_hir::{self as hir, AssocItemKind, ImplItemKind, ItemKind};
+use rustc_hir::{self as hir, AssocItemKind, ImplItemKind, ItemKind, TraitItemKind};
 use rustc_hir_pretty as pretty;
-use rustc_middle::ty::{DefIdTree, List};
-use rustc_span::symbol::Ident;
+use rustc_middle::ty::{AssocKind, BindingMode, DefIdTree, List};
+use rustc_span::symbol::{sym, Ident};
 use rustc_span::DUMMY_SP;
 
+use crate::flags::Flag;
 use crate::spec::SpecType;
+use crate::std_items::StdItem::*;
 use crate::ty::all_generic_params_of;
 use stainless_data::ast as st;
 
@@ -55,6 +57,11 @@ impl FnItem {
   fn is_spec_fn(&self) -> bool {
     self.span.from_expansion() && self.spec_type.is_some()
   }
+
+  /// Invariants of ADTs are spec functions, but are extracted as functions of their own.
+  fn is_invariant_fn(&self) -> bool {
+    self.is_spec_fn() && self.spec_type == Some(SpecType::Invariant)
+  }
 }
 
 /// Top-level extraction
@@ -77,6 +84,11 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
           let path_str = pretty_path(path);
           path_str.starts_with("::std::prelude::v") || path_str.starts_with("stainless")
         }
+        // Derived impls, e.g. of `PartialEq`, are handled where their traits are used
+        ItemKind::Impl { .. } => item
+          .attrs
+          .iter()
+          .any(|attr| attr.check_name(sym::automatically_derived)),
         // TODO: Quick fix to filter our synthetic functions
         // ItemKind::Fn(..) if !item.attrs.is_empty() => true,
         _ => false,
@@ -86,11 +98,48 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     struct ItemVisitor<'xtor, 'l, 'tcx> {
       xtor: &'xtor mut BaseExtractor<'l, 'tcx>,
       adts: Vec<&'tcx hir::Item<'tcx>>,
+      traits: Vec<DefId>,
+      trait_impls: Vec<DefId>,
       functions: Vec<FnItem>,
       // Maps the user-function to its spec functions
       specs: HashMap<DefId, Vec<FnItem>>,
     }
 
+    impl<'xtor, 'l, 'tcx> ItemVisitor<'xtor, 'l, 'tcx> {
+      /// Record the functions of an impl block or a trait, associating the sibling spec
+      /// functions with the functions they specify.
+      fn visit_assoc_fns<I>(&mut self, fn_items: I)
+      where
+        I: Iterator<Item = (Ident, HirId, Span)>,
+      {
+        // Get all functions by their identifier
+        let fns_by_identifier: HashMap<Ident, FnItem> = fn_items
+          .map(|(ident, hir_id, span)| {
+            let def_id = self.xtor.tcx.hir().local_def_id(hir_id).to_def_id();
+            (ident, FnItem::new(def_id, ident, span))
+          })
+          .collect();
+
+        let (specs, fns): (Vec<&FnItem>, Vec<&FnItem>) = fns_by_identifier
+          .values()
+          .partition(|&fn_item| fn_item.is_spec_fn() && !fn_item.is_invariant_fn());
+        self.functions.extend(fns);
+
+        specs.iter().for_each(|&&spec_item| {
+          if let Some(fn_item) = spec_item
+            .spec_fn_name
+            .and_then(|fn_ident| fns_by_identifier.get(&fn_ident))
+          {
+            self
+              .specs
+              .entry(fn_item.def_id)
+              .or_insert_with(Vec::new)
+              .push(spec_item)
+          }
+        });
+      }
+    }
+
     impl<'xtor, 'l, 'tcx> ItemLikeVisitor<'tcx> for ItemVisitor<'xtor, 'l, 'tcx> {
       fn visit_item(&mut self, item: &'tcx hir::Item<'tcx>) {
         match item.kind {
@@ -134,40 +183,34 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
             }
           }
 
-          ItemKind::Impl { items, .. } => {
-            // Get all functions in the impl by their identifier
-            let fns_by_identifier: HashMap<Ident, FnItem> = items
-              .into_iter()
-              .filter_map(|item| match &item.kind {
-                AssocItemKind::Fn { .. } => Some((
-                  item.ident,
-                  FnItem::new(
-                    self.xtor.tcx.hir().local_def_id(item.id.hir_id).to_def_id(),
-                    item.ident,
-                    item.span,
-                  ),
-                )),
-                _ => None,
-              })
-              .collect();
+          ItemKind::Trait(.., items) => {
+            let def_id = self.xtor.tcx.hir().local_def_id(item.hir_id).to_def_id();
+            eprintln!("  - Trait {}", self.xtor.tcx.def_path_str(def_id));
+            self.traits.push(def_id);
+
+            self.visit_assoc_fns(
+              items
+                .iter()
+                .filter(|item| matches!(item.kind, AssocItemKind::Fn { .. }))
+                .map(|item| (item.ident, item.id.hir_id, item.span)),
+            );
+          }
 
-            let (specs, fns): (Vec<&FnItem>, Vec<&FnItem>) = fns_by_identifier
-              .values()
-              .partition(|&fn_item| fn_item.is_spec_fn());
-            self.functions.extend(fns);
-
-            specs.iter().for_each(|&&spec_item| {
-              if let Some(fn_item) = spec_item
-                .spec_fn_name
-                .and_then(|fn_ident| fns_by_identifier.get(&fn_ident))
-              {
-                self
-                  .specs
-                  .entry(fn_item.def_id)
-                  .or_insert_with(Vec::new)
-                  .push(spec_item)
+          ItemKind::Impl { items, .. } => {
+            let def_id = self.xtor.tcx.hir().local_def_id(item.hir_id).to_def_id();
+            if let Some(trait_def_id) = self.xtor.tcx.trait_id_of_impl(def_id) {
+              if self.xtor.is_user_trait(trait_def_id) {
+                eprintln!("  - Impl {}", self.xtor.tcx.def_path_str(def_id));
+                self.trait_impls.push(def_id);
               }
-            });
+            }
+
+            self.visit_assoc_fns(
+              items
+                .iter()
+                .filter(|item| matches!(item.kind, AssocItemKind::Fn { .. }))
+                .map(|item| (item.ident, item.id.hir_id, item.span)),
+            );
           }
 
           _ => {
@@ -176,9 +219,20 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
         }
       }
 
-      /// Unsupported
       fn visit_trait_item(&mut self, trait_item: &'tcx hir::TraitItem<'tcx>) {
-        self.xtor.unsupported(trait_item.span, "Trait item");
+        match trait_item {
+          // Ignore fn items in traits because they are already treated
+          // when the entire trait is extracted.
+          hir::TraitItem {
+            kind: TraitItemKind::Fn(..),
+            ..
+          } => {}
+
+          // Fail for all other kinds of trait items.
+          _ => self
+            .xtor
+            .unsupported(trait_item.span, "Trait item other than function"),
+        }
       }
 
       fn visit_impl_item(&mut self, impl_item: &'tcx hir::ImplItem<'tcx>) {
@@ -204,6 +258,8 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     let mut visitor = ItemVisitor {
       xtor: self,
       adts: vec![],
+      traits: vec![],
+      trait_impls: vec![],
       functions: vec![],
       specs: HashMap::new(),
     };
@@ -213,6 +269,8 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
 
     let ItemVisitor {
       adts,
+      traits,
+      trait_impls,
       functions,
       specs,
       ..
@@ -225,7 +283,30 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
       self.add_adt(sort.id, sort);
     }
 
+    for def_id in traits {
+      let cd = self.extract_trait(def_id);
+      self.add_class(cd.id, cd);
+    }
+    for def_id in trait_impls {
+      let (cd, laws) = self.extract_trait_impl(def_id);
+      self.add_class(cd.id, cd);
+      for fd in laws {
+        self.add_function(fd.id, fd);
+      }
+    }
+
     for fn_item in functions {
+      // Trait methods without a default implementation are abstract
+      let hir_id = self
+        .tcx
+        .hir()
+        .as_local_hir_id(fn_item.def_id.expect_local());
+      if self.tcx.hir().maybe_body_owned_by(hir_id).is_none() {
+        let fd = self.extract_abstract_fn(fn_item.def_id);
+        self.add_function(fd.id, fd);
+        continue;
+      }
+
       let fn_specs = specs
         .get(&fn_item.def_id)
         .into_iter()
@@ -274,7 +355,13 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
       Some(fun_id) => fun_id,
       None => {
         self.add_function_ref(def_id);
-        self.register_def(def_id)
+        match self.overridden_method(def_id) {
+          Some(overridden_def_id) => {
+            let overridden = self.extract_fn_ref(overridden_def_id);
+            self.register_overriding_def(def_id, overridden)
+          }
+          None => self.register_def(def_id),
+        }
       }
     }
   }
@@ -326,15 +413,36 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     assert!(def_id.is_local());
     let hir_id = tcx.hir().as_local_hir_id(def_id.expect_local());
 
-    // Extract flags
-    let (carrier_flags, mut flags_by_symbol) = self.extract_flags(hir_id);
-    let flags = carrier_flags.to_stainless(f);
+    // Extract flags, overrides of laws being laws themselves
+    let (mut carrier_flags, mut flags_by_symbol) = self.extract_flags(hir_id);
+    let overridden_law = self.overridden_law(def_id);
+    if overridden_law.is_some() {
+      carrier_flags.add(Flag::Law);
+    }
+    if carrier_flags.contains(Flag::Law) {
+      self.check_law(def_id);
+    }
+    let mut flags = carrier_flags.to_stainless(f);
+    let is_invariant = self.is_adt_invariant(def_id);
+    if is_invariant {
+      flags.push(f.IsInvariant().into());
+    }
+    if let Some(class_def_id) = self.class_of_method(def_id) {
+      let class_id = self.get_or_register_def(class_def_id);
+      flags.push(f.IsMethodOf(class_id).into());
+    }
 
     // Extract the function itself
     type Parts<'l> = (Params<'l>, st::Type<'l>, st::Expr<'l>);
-    let (tparams, txtcx) = self.extract_generics(def_id);
+    let (tparams, txtcx) = self.extract_method_generics(def_id);
+    let (evidence_params, evidence) = self.extract_evidence_env(def_id, &txtcx);
+    if is_invariant && !evidence_params.is_empty() {
+      self.unsupported(tcx.def_span(def_id), "Invariants of ADTs with trait bounds");
+    }
     let (params, return_tpe, mut body_expr): Parts<'l> =
       self.enter_body(hir_id, txtcx.clone(), |bxtor| {
+        bxtor.evidence = evidence.clone();
+
         // Register parameters and local bindings in the DefContext
         bxtor.populate_def_context(&mut flags_by_symbol);
 
@@ -346,22 +454,51 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
           .base
           .extract_ty(sig.output(), &bxtor.txtcx, decl.output.span());
 
-        let params: Params<'l> = bxtor
-          .body
+        // Stainless parameters are immutable, so `mut` parameters are passed in under a fresh
+        // name and re-bound as local vars at the top of the body.
+        let mut mut_params = vec![];
+        let body = bxtor.body;
+        let params: Params<'l> = body
           .params
           .iter()
-          .map(|param| &*f.ValDef(bxtor.fetch_var(param.pat.hir_id)))
+          .map(|param| {
+            let var = bxtor.fetch_var(param.pat.hir_id);
+            let mode =
+              bxtor
+                .tables
+                .extract_binding_mode(tcx.sess, param.pat.hir_id, param.pat.span);
+            if let Some(BindingMode::BindByValue(hir::Mutability::Mut)) = mode {
+              let param_var = bxtor.fresh_var(&var.id.id.name, var.tpe);
+              mut_params.push((var, param_var));
+              &*f.ValDef(param_var)
+            } else {
+              &*f.ValDef(var)
+            }
+          })
           .collect();
 
         // Extract the body
         let body_expr = bxtor.hcx.mirror(&bxtor.body.value);
-        let body_expr = bxtor.extract_expr(body_expr);
+        let body_expr = bxtor.extract_body_expr(body_expr);
+        let body_expr = mut_params
+          .into_iter()
+          .rev()
+          .fold(body_expr, |body, (var, param_var)| {
+            f.LetVar(f.ValDef(var), param_var.into(), body).into()
+          });
 
         (params, return_tpe, body_expr)
       });
 
     self.report_unused_flags(hir_id, &flags_by_symbol);
 
+    // An impl overriding a law still has to satisfy the trait's version of it
+    if let Some(law_def_id) = overridden_law {
+      let impl_def_id = tcx.impl_of_method(def_id).unwrap();
+      let super_law = self.extract_super_law_call(impl_def_id, law_def_id, &txtcx, &params);
+      body_expr = f.And(vec![super_law, body_expr]).into();
+    }
+
     if let Some(measure_spec_def_id) = measure_spec_function {
       // The measure function generated by the macro has a Unit return type to
       // deal with the unknown type of the measure. The expression has a
@@ -371,7 +508,7 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
       if let st::Expr::Block(st::Block {
         exprs,
         last: st::Expr::UnitLiteral(st::UnitLiteral {}),
-      }) = self.extract_spec_fn(measure_spec_def_id, &txtcx, &params, None)
+      }) = self.extract_spec_fn(measure_spec_def_id, &txtcx, &params, &evidence, None)
       {
         // Create a block that returns its last expression
         let (last, other_exprs) = exprs.split_last().expect("No measure provided.");
@@ -387,7 +524,7 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
 
     let spec_exprs = pre_spec_functions
       .into_iter()
-      .map(|spec_def_id| self.extract_spec_fn(spec_def_id, &txtcx, &params, None))
+      .map(|spec_def_id| self.extract_spec_fn(spec_def_id, &txtcx, &params, &evidence, None))
       .collect::<Vec<_>>();
     if !spec_exprs.is_empty() {
       body_expr = f.Require(f.make_and(spec_exprs), body_expr).into();
@@ -395,18 +532,25 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
 
     let return_var = &*f.Variable(self.fresh_id("ret".into()), return_tpe, vec![]);
     let return_vd = f.ValDef(return_var);
-    let spec_exprs = post_spec_functions
+    let mut spec_exprs = post_spec_functions
       .into_iter()
-      .map(|spec_def_id| self.extract_spec_fn(spec_def_id, &txtcx, &params, Some(return_var)))
+      .map(|spec_def_id| {
+        self.extract_spec_fn(spec_def_id, &txtcx, &params, &evidence, Some(return_var))
+      })
       .collect::<Vec<_>>();
+    if overridden_law.is_some() {
+      spec_exprs.push(return_var.into());
+    }
     if !spec_exprs.is_empty() {
       body_expr = f
         .Ensuring(body_expr, f.Lambda(vec![return_vd], f.make_and(spec_exprs)))
         .into();
     }
 
-    // Wrap it all up in a Stainless function
+    // Wrap it all up in a Stainless function, passing evidence after the normal parameters
+    let params = params.into_iter().chain(evidence_params).collect();
     let fun_id = self.extract_fn_ref(def_id);
+    self.set_pos(fun_id, tcx.def_span(def_id));
     f.FunDef(fun_id, tparams, params, return_tpe, body_expr, flags)
   }
 
@@ -416,6 +560,7 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     def_id: DefId,
     original_txtcx: &TyExtractionCtxt<'l>,
     original_params: &[&'l st::ValDef<'l>],
+    original_evidence: &Evidence<'l, 'tcx>,
     return_var: Option<&'l st::Variable<'l>>,
   ) -> st::Expr<'l> {
     // Spec functions are inner functions within the actual function being specified.
@@ -446,6 +591,11 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
 
     let hir_id = self.tcx.hir().as_local_hir_id(def_id.expect_local());
     self.enter_body(hir_id, txtcx, |bxtor| {
+      // Spec functions have the same trait bounds as the surrounding function
+      bxtor.evidence = original_evidence.clone();
+      // Only postconditions may refer to the old values of parameters
+      bxtor.in_postcondition = return_var.is_some();
+
       // Correlate term parameters
       let mut param_hir_ids: Vec<HirId> = bxtor
         .body
@@ -482,6 +632,27 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     })
   }
 
+  /// Whether the given function is the invariant generated by `#[invariant]` on an ADT.
+  fn is_adt_invariant(&self, def_id: DefId) -> bool {
+    let name = self.tcx.item_name(def_id);
+    self.tcx.def_span(def_id).from_expansion()
+      && matches!(
+        SpecType::parse_spec_type_fn_name(&name.as_str()),
+        Some((SpecType::Invariant, _))
+      )
+  }
+
+  /// Find the invariant of a local ADT, which lives in an inherent impl of the ADT.
+  fn adt_invariant(&self, def_id: DefId) -> Option<DefId> {
+    self
+      .tcx
+      .inherent_impls(def_id)
+      .iter()
+      .flat_map(|&impl_def_id| self.tcx.associated_items(impl_def_id).in_definition_order())
+      .find(|item| item.kind == AssocKind::Fn && self.is_adt_invariant(item.def_id))
+      .map(|item| item.def_id)
+  }
+
   /// Extract the definition ID of an ADT.
   ///
   /// If the ADT is already in the mappings, it will reuse the ID, otherwise the
@@ -506,6 +677,13 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     match sort_opt {
       Some(sort) => sort,
       None => {
+        // Option and Result are mapped to their models of the Stainless library
+        if let Some(item @ OptionType) | Some(item @ ResultType) =
+          self.std_items.def_to_item_opt(def_id)
+        {
+          return self.extract_model_adt(item, def_id);
+        }
+
         let f = self.factory();
         let adt_id = self.register_def(def_id);
         let adt_def = self.tcx.adt_def(def_id);
@@ -514,7 +692,7 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
         let local_def_id_opt = def_id.as_local();
 
         // Extract flags for local def ids.
-        let (flags, mut flags_by_symbol, hir_id_opt) = match local_def_id_opt {
+        let (mut flags, mut flags_by_symbol, hir_id_opt) = match local_def_id_opt {
           Some(local_def_id) => {
             let hir_id = self.tcx.hir().as_local_hir_id(local_def_id);
             let (carrier_flags, by_symbol) = self.extract_flags(hir_id);
@@ -526,6 +704,12 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
           _ => (vec![], HashMap::new(), None),
         };
 
+        // Stainless checks the invariant wherever a value is constructed
+        if let Some(invariant_def_id) = local_def_id_opt.and_then(|_| self.adt_invariant(def_id)) {
+          let invariant_id = self.extract_fn_ref(invariant_def_id);
+          flags.push(f.HasADTInvariant(invariant_id).into());
+        }
+
         // Extract generics
         let (tparams, txtcx) = self.extract_generics(def_id);
 
@@ -551,11 +735,17 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
                 &*f.ValDef(field)
               })
               .collect();
+            if def_id.is_local() {
+              self.set_pos(cons_id, self.tcx.def_span(variant.def_id));
+            }
             &*f.ADTConstructor(cons_id, adt_id, fields)
           })
           .collect();
 
         hir_id_opt.map(|hir_id| self.report_unused_flags(hir_id, &flags_by_symbol));
+        if def_id.is_local() {
+          self.set_pos(adt_id, self.tcx.def_span(def_id));
+        }
         f.ADTSort(adt_id, tparams, constructors, flags)
       }
     }
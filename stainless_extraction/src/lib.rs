@@ -14,13 +14,18 @@ extern crate rustc_middle;
 extern crate rustc_session;
 extern crate rustc_span;
 extern crate rustc_target;
+extern crate rustc_trait_selection;
 extern crate rustc_ty;
 
+mod arithmetic;
 mod bindings;
+mod classes;
 mod expr;
 mod flags;
 mod krate;
 mod literal;
+mod models;
+mod returns;
 mod spec;
 mod std_items;
 mod ty;
@@ -35,11 +40,12 @@ use rustc_hir::intravisit::{self, NestedVisitorMap, Visitor};
 use rustc_hir::{self as hir, HirId};
 use rustc_infer::infer::{InferCtxt, TyCtxtInferExt};
 use rustc_middle::span_bug;
-use rustc_middle::ty::{TyCtxt, TypeckTables};
+use rustc_middle::ty::{TraitRef, TyCtxt, TypeckTables};
 use rustc_span::{MultiSpan, Span};
 
 use stainless_data::ast as st;
 
+use arithmetic::OverflowFnKey;
 use bindings::DefContext;
 use std_items::StdItems;
 use ty::TyExtractionCtxt;
@@ -53,29 +59,50 @@ pub fn extract_crate<'l, 'tcx: 'l>(
 ) -> st::Symbols<'l> {
   let extraction = Box::new(Extraction::new(factory));
   let std_items = Rc::new(StdItems::collect(tcx));
-  let mut xtor = BaseExtractor::new(tcx, std_items, extraction);
+  let pointer_width = pointer_width(tcx);
+  let mut xtor = BaseExtractor::new(tcx, std_items, pointer_width, extraction);
   xtor.process_crate(crate_name);
 
-  let (adts, functions) = xtor.into_result();
+  let symbols = xtor.into_result();
 
   // Output extracted Stainless program
-  eprintln!("[ Extracted ADTs and functions ]");
-  for adt in &adts {
+  eprintln!("[ Extracted ADTs, classes and functions ]");
+  for adt in symbols.sorts.values() {
     eprintln!(" - ADT {}", adt.id);
     // eprintln!(" > {:#?}", adt);
   }
-  for fd in &functions {
+  for cd in symbols.classes.values() {
+    eprintln!(" - Class {}", cd.id);
+  }
+  for fd in symbols.functions.values() {
     eprintln!(" - Fun {}", fd.id);
     // eprintln!(" > {:#?}", fd);
   }
   eprintln!();
 
-  st::Symbols::new(adts, functions)
+  symbols
+}
+
+/// The width of `isize` and `usize`, which is the target's pointer width, unless overridden by
+/// `RUSTSTAINLESS_POINTER_WIDTH` to verify the crate for targets of another width.
+fn pointer_width(tcx: TyCtxt<'_>) -> u64 {
+  match std::env::var("RUSTSTAINLESS_POINTER_WIDTH") {
+    Ok(width) => match width.parse() {
+      Ok(width @ 16) | Ok(width @ 32) | Ok(width @ 64) => width,
+      _ => tcx.sess.fatal(&format!(
+        "Invalid pointer width {}, expected one of 16, 32 or 64",
+        width
+      )),
+    },
+    Err(_) => tcx.data_layout.pointer_size.bits(),
+  }
 }
 
 /// Helpful type aliases
 type StainlessSymId<'l> = &'l st::SymbolIdentifier<'l>;
 type Params<'l> = Vec<&'l st::ValDef<'l>>;
+/// Evidence of trait bounds being satisfied, as available in some body
+type Evidence<'l, 'tcx> = Vec<(TraitRef<'tcx>, st::Expr<'l>)>;
 
 /// A mapping between Rust ids and Stainless ids
 struct SymbolMapping<'l> {
@@ -92,6 +119,10 @@ struct Extraction<'l> {
   adts: HashMap<StainlessSymId<'l>, &'l st::ADTSort<'l>>,
   function_refs: HashSet<DefId>,
   functions: HashMap<StainlessSymId<'l>, &'l st::FunDef<'l>>,
+  classes: HashMap<StainlessSymId<'l>, &'l st::ClassDef<'l>>,
+  overflow_fns: HashMap<OverflowFnKey, &'l st::FunDef<'l>>,
+  positions: HashMap<i32, st::Position>,
+  overrides: HashMap<i32, i32>,
 }
 
 impl<'l> Extraction<'l> {
@@ -107,6 +138,10 @@ impl<'l> Extraction<'l> {
       adts: HashMap::new(),
       function_refs: HashSet::new(),
       functions: HashMap::new(),
+      classes: HashMap::new(),
+      overflow_fns: HashMap::new(),
+      positions: HashMap::new(),
+      overrides: HashMap::new(),
     }
   }
 
@@ -116,30 +151,68 @@ impl<'l> Extraction<'l> {
     let id = self.factory.Identifier(name, global_id, local_id);
     self.factory.SymbolIdentifier(id, symbol_path)
   }
+
+  fn fresh_overriding_id(
+    &mut self,
+    name: String,
+    overridden: StainlessSymId<'l>,
+  ) -> StainlessSymId<'l> {
+    let global_id = self.mapping.global_id_counter.fresh(&());
+    let local_id = self.mapping.local_id_counter.fresh(&name);
+    let id = self.factory.Identifier(name, global_id, local_id);
+    let overridden_gid = overridden.id.globalId;
+    let symbol_id = *self
+      .overrides
+      .get(&overridden_gid)
+      .unwrap_or(&overridden_gid);
+    self.overrides.insert(global_id, symbol_id);
+    self
+      .factory
+      .SymbolIdentifier(id, overridden.symbol_path.clone())
+  }
 }
 
 /// Extractor combines rustc state with extraction state
 struct BaseExtractor<'l, 'tcx: 'l> {
   tcx: TyCtxt<'tcx>,
   std_items: Rc<StdItems>,
+  pointer_width: u64,
   extraction: Option<Box<Extraction<'l>>>,
 }
 
 impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
-  fn new(tcx: TyCtxt<'tcx>, std_items: Rc<StdItems>, extraction: Box<Extraction<'l>>) -> Self {
+  fn new(
+    tcx: TyCtxt<'tcx>,
+    std_items: Rc<StdItems>,
+    pointer_width: u64,
+    extraction: Box<Extraction<'l>>,
+  ) -> Self {
     Self {
       tcx,
       std_items,
+      pointer_width,
       extraction: Some(extraction),
     }
   }
 
-  fn into_result(self) -> (Vec<&'l st::ADTSort<'l>>, Vec<&'l st::FunDef<'l>>) {
-    self.with_extraction(|xt| {
-      let adts: Vec<&st::ADTSort> = xt.adts.values().copied().collect();
-      let functions: Vec<&st::FunDef> = xt.functions.values().copied().collect();
-      (adts, functions)
-    })
+  fn into_result(self) -> st::Symbols<'l> {
+    let xt = self.extraction.expect("BodyExtractor active");
+    let adts: Vec<&st::ADTSort> = xt.adts.values().copied().collect();
+    let functions: Vec<&st::FunDef> = xt.functions.values().copied().collect();
+    let classes: Vec<&st::ClassDef> = xt.classes.values().copied().collect();
+
+    // Local bindings keep their names from the source, which Stainless may disambiguate
+    let local_names = xt
+      .mapping
+      .hid_to_stid
+      .values()
+      .map(|id| (id.id.globalId, id.id.name.clone()))
+      .collect();
+
+    st::Symbols::new(adts, functions, classes)
+      .with_positions(xt.positions)
+      .with_local_names(local_names)
+      .with_overrides(xt.overrides)
   }
 
   #[inline]
@@ -201,6 +274,20 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     })
   }
 
+  /// Register a method overriding the given method of a trait, sharing the latter's symbol.
+  fn register_overriding_def(
+    &mut self,
+    def_id: DefId,
+    overridden: StainlessSymId<'l>,
+  ) -> StainlessSymId<'l> {
+    let name = self.tcx.item_name(def_id).to_string();
+    self.with_extraction_mut(|xt| {
+      let id = xt.fresh_overriding_id(name, overridden);
+      assert!(xt.mapping.did_to_stid.insert(def_id, id).is_none());
+      id
+    })
+  }
+
   #[inline]
   fn get_id_from_def(&self, def_id: DefId) -> Option<StainlessSymId<'l>> {
     self.with_extraction(|xt| xt.mapping.did_to_stid.get(&def_id).copied())
@@ -223,7 +310,13 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     })
   }
 
-  /// ADTs and Functions
+  fn get_or_register_hir(&mut self, hir_id: HirId, name: String) -> StainlessSymId<'l> {
+    self
+      .with_extraction(|xt| xt.mapping.hid_to_stid.get(&hir_id).copied())
+      .unwrap_or_else(|| self.register_hir(hir_id, name))
+  }
+
+  /// ADTs, classes and Functions
 
   fn add_adt(&mut self, id: StainlessSymId<'l>, adt: &'l st::ADTSort<'l>) {
     self.with_extraction_mut(|xt| {
@@ -231,6 +324,12 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     })
   }
 
+  fn add_class(&mut self, id: StainlessSymId<'l>, cd: &'l st::ClassDef<'l>) {
+    self.with_extraction_mut(|xt| {
+      assert!(xt.classes.insert(id, cd).is_none());
+    })
+  }
+
   fn add_function_ref(&mut self, def_id: DefId) {
     self.with_extraction_mut(|xt| {
       assert!(xt.function_refs.insert(def_id));
@@ -243,6 +342,35 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     })
   }
 
+  /// Source positions
+
+  /// The source range of a span, as it appears in the user's code.
+  fn position(&self, span: Span) -> Option<st::Position> {
+    let span = span.source_callsite();
+    if span.is_dummy() {
+      return None;
+    }
+    let source_map = self.tcx.sess.source_map();
+    let (lo, hi) = (
+      source_map.lookup_char_pos(span.lo()),
+      source_map.lookup_char_pos(span.hi()),
+    );
+    Some(st::Position {
+      file: lo.file.name.to_string(),
+      line_from: lo.line as i32,
+      col_from: lo.col.0 as i32 + 1,
+      line_to: hi.line as i32,
+      col_to: hi.col.0 as i32 + 1,
+    })
+  }
+
+  /// Record the position of the given span as that of an extracted definition.
+  fn set_pos(&mut self, id: &'l st::SymbolIdentifier<'l>, span: Span) {
+    if let Some(pos) = self.position(span) {
+      self.with_extraction_mut(|xt| xt.positions.insert(id.id.globalId, pos));
+    }
+  }
+
   /// Get a BodyExtractor for some item with a body (like a function)
   fn enter_body<T, F>(&mut self, hir_id: HirId, txtcx: TyExtractionCtxt<'l>, f: F) -> T
   where
@@ -277,6 +405,10 @@ struct BodyExtractor<'a, 'l, 'tcx: 'l> {
   body: &'tcx hir::Body<'tcx>,
   txtcx: TyExtractionCtxt<'l>,
   dcx: DefContext<'l>,
+  evidence: Evidence<'l, 'tcx>,
+  in_postcondition: bool,
+  early_exits: Vec<&'tcx hir::Expr<'tcx>>,
+  try_values: HashMap<Span, st::Expr<'l>>,
 }
 
 impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
@@ -291,6 +423,7 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
     let base = BaseExtractor::new(
       tcx,
       base.std_items.clone(),
+      base.pointer_width,
       extraction.expect("Waiting for another BodyExtractor to finish"),
     );
 
@@ -313,6 +446,10 @@ impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
       body,
       txtcx,
       dcx: DefContext::new(),
+      evidence: vec![],
+      in_postcondition: false,
+      early_exits: vec![],
+      try_values: HashMap::new(),
     }
   }
 
@@ -1,6 +1,4 @@
-use std::convert::TryFrom;
-
-use rustc_middle::mir::interpret::ConstValue;
+use rustc_middle::mir::interpret::{ConstValue, Scalar};
 use rustc_middle::ty::{self, ConstKind, TyKind};
 use rustc_target::abi;
 
@@ -16,6 +14,19 @@ pub(super) enum Literal {
 }
 
 impl Literal {
+  /// A bitvector literal of the given signedness and size, made of the lowest bits of `value`
+  pub(super) fn bv(signed: bool, size: u64, value: u128) -> Self {
+    let value = value & (std::u128::MAX >> (128 - size));
+    if signed {
+      Literal::Int {
+        value: value as i128,
+        size,
+      }
+    } else {
+      Literal::Uint { value, size }
+    }
+  }
+
   pub(super) fn as_st_literal<'l>(&self, f: &'l st::Factory) -> st::Expr<'l> {
     match self {
       Literal::Unit => f.UnitLiteral().into(),
@@ -30,36 +41,55 @@ impl Literal {
       Literal::String(value) => f.StringLiteral(value.clone()).into(),
     }
   }
-}
 
-impl<'tcx> TryFrom<&'tcx ty::Const<'tcx>> for Literal {
-  type Error = ();
-
-  fn try_from(konst: &'tcx ty::Const<'tcx>) -> Result<Self, Self::Error> {
+  /// Convert a constant of a primitive type. `isize` and `usize` constants are given the pointer
+  /// width used for extraction, as long as their values fit.
+  pub(super) fn from_const<'tcx>(
+    konst: &'tcx ty::Const<'tcx>,
+    pointer_width: u64,
+  ) -> Result<Self, ()> {
     match konst.ty.kind {
       _ if konst.ty.is_unit() => Ok(Literal::Unit),
       TyKind::Bool => {
         let value = konst.val.try_to_bits(abi::Size::from_bits(1)).unwrap() == 1;
         Ok(Literal::Bool(value))
       }
-      // TODO: Handle `isize` and `usize`
-      TyKind::Int(int_ty) => int_ty
-        .bit_width()
-        .and_then(|size| {
+      TyKind::Int(int_ty) => match int_ty.bit_width() {
+        Some(size) => {
           let value = konst.val.try_to_bits(abi::Size::from_bits(size));
-          value.map(|value| Literal::Int {
-            value: value as i128,
-            size,
-          })
-        })
-        .ok_or(()),
-      TyKind::Uint(uint_ty) => uint_ty
-        .bit_width()
-        .and_then(|size| {
+          value
+            .map(|value| Literal::Int {
+              value: value as i128,
+              size,
+            })
+            .ok_or(())
+        }
+        None => {
+          let (value, target_size) = pointer_sized_bits(konst)?;
+          let shift = 128 - target_size;
+          let value = ((value << shift) as i128) >> shift;
+          let bound = 1i128 << (pointer_width - 1);
+          if value >= -bound && value < bound {
+            Ok(Literal::bv(true, pointer_width, value as u128))
+          } else {
+            Err(())
+          }
+        }
+      },
+      TyKind::Uint(uint_ty) => match uint_ty.bit_width() {
+        Some(size) => {
           let value = konst.val.try_to_bits(abi::Size::from_bits(size));
-          value.map(|value| Literal::Uint { value, size })
-        })
-        .ok_or(()),
+          value.map(|value| Literal::Uint { value, size }).ok_or(())
+        }
+        None => {
+          let (value, _) = pointer_sized_bits(konst)?;
+          if value >> pointer_width == 0 {
+            Ok(Literal::bv(false, pointer_width, value))
+          } else {
+            Err(())
+          }
+        }
+      },
       TyKind::Ref(
         _,
         ty::TyS {
@@ -78,3 +108,11 @@ impl<'tcx> TryFrom<&'tcx ty::Const<'tcx>> for Literal {
     }
   }
 }
+
+/// The bits of an `isize` or `usize` constant, along with the target's pointer width
+fn pointer_sized_bits(konst: &ty::Const<'_>) -> Result<(u128, u64), ()> {
+  match konst.val.try_to_scalar() {
+    Some(Scalar::Raw { data, size }) => Ok((data, u64::from(size) * 8)),
+    _ => Err(()),
+  }
+}
@@ -0,0 +1,532 @@
+use super::std_items::StdItem;
+use super::std_items::StdItem::*;
+use super::*;
+
+use rustc_hair::hair::ExprRef;
+use rustc_middle::ty::subst::SubstsRef;
+
+use stainless_data::ast as st;
+
+// Rust's `Option` and `Result` are extracted as models of the `Option` and `Either` of the
+// Stainless library, rather than as opaque external ADTs. Models are named like the Rust
+// definitions, so that they read naturally in diagnostics, but have the symbol paths of the
+// library definitions they stand for, which is how Stainless recognises them. `Result<T, E>`
+// becomes `Either[T, E]`, with `Ok` as `Left` and `Err` as `Right`, which keeps the type
+// parameters in the same order.
+//
+// The common combinators of both types are extracted as model functions, which stand for the
+// library methods with the same meaning, if there is one. Their bodies define them, and like the
+// contracts of the library methods, theirs state how the variant of the result depends on that of
+// the receiver, so that callers need not unfold the bodies for the most common facts.
+// Since we send our own definitions, Stainless also verifies them.
+
+/// A shorthand for building model definitions
+#[derive(Clone, Copy)]
+struct Models<'l> {
+  f: &'l st::Factory,
+  option: &'l st::ADTSort<'l>,
+  result: &'l st::ADTSort<'l>,
+}
+
+impl<'l> Models<'l> {
+  /// The name of the library definition the model of `Option` or `Result` stands for
+  fn library_name(&self, is_option: bool) -> &'static str {
+    if is_option {
+      "Option"
+    } else {
+      "Either"
+    }
+  }
+
+  fn sort(&self, is_option: bool) -> &'l st::ADTSort<'l> {
+    if is_option {
+      self.option
+    } else {
+      self.result
+    }
+  }
+
+  fn constructor(&self, is_option: bool, name: &str) -> &'l st::ADTConstructor<'l> {
+    self
+      .sort(is_option)
+      .constructors
+      .iter()
+      .copied()
+      .find(|cons| cons.id.id.name == name)
+      .unwrap_or_else(|| panic!("Model constructor {} is missing", name))
+  }
+
+  fn option_type(&self, t: st::Type<'l>) -> st::Type<'l> {
+    self.f.ADTType(self.option.id, vec![t]).into()
+  }
+
+  fn result_type(&self, t: st::Type<'l>, e: st::Type<'l>) -> st::Type<'l> {
+    self.f.ADTType(self.result.id, vec![t, e]).into()
+  }
+
+  fn is(&self, expr: st::Expr<'l>, is_option: bool, name: &str) -> st::Expr<'l> {
+    let cons = self.constructor(is_option, name);
+    self.f.IsConstructor(expr, cons.id).into()
+  }
+
+  fn get(&self, expr: st::Expr<'l>, is_option: bool, name: &str) -> st::Expr<'l> {
+    let cons = self.constructor(is_option, name);
+    self.f.ADTSelector(expr, cons.fields[0].v.id).into()
+  }
+
+  fn make(
+    &self,
+    is_option: bool,
+    name: &str,
+    tps: Vec<st::Type<'l>>,
+    args: Vec<st::Expr<'l>>,
+  ) -> st::Expr<'l> {
+    let cons = self.constructor(is_option, name);
+    self.f.ADT(cons.id, tps, args).into()
+  }
+}
+
+/// The method of the library's `Option` or `Either` that a model function stands for, if any.
+/// `expect` is extracted as `unwrap`, which is why it has no library method of its own.
+fn library_method(item: StdItem) -> Option<&'static str> {
+  match item {
+    OptionIsSomeFn => Some("isDefined"),
+    OptionIsNoneFn => Some("isEmpty"),
+    OptionUnwrapFn => Some("get"),
+    OptionUnwrapOrFn => Some("getOrElse"),
+    OptionOrFn => Some("orElse"),
+    OptionMapFn => Some("map"),
+    OptionAndThenFn => Some("flatMap"),
+    OptionFilterFn => Some("filter"),
+    ResultIsOkFn => Some("isLeft"),
+    ResultIsErrFn => Some("isRight"),
+    _ => None,
+  }
+}
+
+/// The parts of a model function besides its type parameters and receiver
+struct ModelFnParts<'l> {
+  params: Params<'l>,
+  return_tpe: st::Type<'l>,
+  pre: Option<st::Expr<'l>>,
+  body: st::Expr<'l>,
+  post: Option<(&'l st::Variable<'l>, st::Expr<'l>)>,
+}
+
+impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
+  /// A fresh identifier for a definition that models part of the standard library.
+  pub(super) fn model_id(&mut self, path: &[&str]) -> StainlessSymId<'l> {
+    let name = path.last().unwrap().to_string();
+    let symbol_path = std::iter::once(&"std")
+      .chain(path)
+      .map(|s| s.to_string())
+      .collect();
+    self.with_extraction_mut(|xt| xt.fresh_id(name, symbol_path))
+  }
+
+  /// A fresh identifier for a model with the given name that stands for a definition of the
+  /// Stainless library.
+  fn library_model_id(&mut self, name: &str, library_path: &[&str]) -> StainlessSymId<'l> {
+    let symbol_path = ["stainless", "lang"]
+      .iter()
+      .chain(library_path)
+      .map(|s| s.to_string())
+      .collect();
+    self.with_extraction_mut(|xt| xt.fresh_id(name.into(), symbol_path))
+  }
+
+  pub(super) fn model_var(&mut self, path: &[&str], tpe: st::Type<'l>) -> &'l st::Variable<'l> {
+    let id = self.model_id(path);
+    self.factory().Variable(id, tpe, vec![])
+  }
+
+  /// Extract `Option` or `Result` as the corresponding model ADT.
+  pub(super) fn extract_model_adt(&mut self, item: StdItem, def_id: DefId) -> &'l st::ADTSort<'l> {
+    let f = self.factory();
+    let (sort_name, library_name, tparam_names): (&str, &str, &[&str]) = match item {
+      OptionType => ("Option", "Option", &["T"]),
+      ResultType => ("Result", "Either", &["T", "E"]),
+      _ => unreachable!(),
+    };
+
+    let sort_id = self.library_model_id(sort_name, &[library_name]);
+    self.with_extraction_mut(|xt| xt.mapping.did_to_stid.insert(def_id, sort_id));
+    let tparams: Vec<&'l st::TypeParameter<'l>> = tparam_names
+      .iter()
+      .map(|name| &*f.TypeParameter(self.model_id(&[sort_name, name]), vec![]))
+      .collect();
+
+    // Map each variant to its library constructor and the type parameter of its field, if any.
+    // Fields are named like those of tuple variants, and have the paths of the library fields.
+    let constructors = self
+      .tcx
+      .adt_def(def_id)
+      .variants
+      .iter()
+      .map(|variant| {
+        let (cons_name, library_name, field) = match &*variant.ident.as_str() {
+          "None" => ("None", "None", None),
+          "Some" => ("Some", "Some", Some(("v", 0))),
+          "Ok" => ("Ok", "Left", Some(("content", 0))),
+          "Err" => ("Err", "Right", Some(("content", 1))),
+          name => unexpected(variant.ident.span, format!("Unknown variant {}", name)),
+        };
+        let cons_id = self.library_model_id(cons_name, &[library_name]);
+        self.with_extraction_mut(|xt| xt.mapping.did_to_stid.insert(variant.def_id, cons_id));
+        let fields = field
+          .map(|(field_name, index)| {
+            let field_id = self.library_model_id("_0", &[library_name, field_name]);
+            &*f.ValDef(f.Variable(field_id, tparams[index].into(), vec![]))
+          })
+          .into_iter()
+          .collect();
+        &*f.ADTConstructor(cons_id, sort_id, fields)
+      })
+      .collect();
+
+    let tparams = tparams
+      .into_iter()
+      .map(|tparam| &*f.TypeParameterDef(tparam))
+      .collect();
+    let sort = f.ADTSort(sort_id, tparams, constructors, vec![f.Library().into()]);
+    self.add_adt(sort_id, sort);
+    sort
+  }
+
+  /// Extract the model function that a method of `Option` or `Result` maps to.
+  pub(super) fn extract_model_fn(&mut self, item: StdItem) -> &'l st::FunDef<'l> {
+    let def_id = self.std_items.item_to_def(item);
+    let fd_opt = self.with_extraction(|xt| {
+      xt.mapping
+        .did_to_stid
+        .get(&def_id)
+        .and_then(|id| xt.functions.get(id).copied())
+    });
+    if let Some(fd) = fd_opt {
+      return fd;
+    }
+
+    let f = self.factory();
+    let models = Models {
+      f,
+      option: self.extract_adt(self.std_items.item_to_def(OptionType)),
+      result: self.extract_adt(self.std_items.item_to_def(ResultType)),
+    };
+    let is_option = item.is_option_fn();
+    let owner = models.sort(is_option).id.id.name.as_str();
+    let name = item.name();
+
+    // The type parameters of the owning type come first, followed by those of the method
+    let tparam_names: &[&str] = match item {
+      OptionMapFn | OptionAndThenFn => &["T", "U"],
+      OptionOkOrFn => &["T", "E"],
+      ResultMapFn | ResultAndThenFn => &["T", "E", "U"],
+      ResultMapErrFn => &["T", "E", "F"],
+      _ if is_option => &["T"],
+      _ => &["T", "E"],
+    };
+    let tparams: Vec<&'l st::TypeParameter<'l>> = tparam_names
+      .iter()
+      .map(|tparam_name| &*f.TypeParameter(self.model_id(&[owner, name, tparam_name]), vec![]))
+      .collect();
+    let tps: Vec<st::Type<'l>> = tparams.iter().map(|&tparam| tparam.into()).collect();
+    let recv_tpe = if is_option {
+      models.option_type(tps[0])
+    } else {
+      models.result_type(tps[0], tps[1])
+    };
+    let recv = self.model_var(&[owner, name, "self"], recv_tpe);
+    let r: st::Expr<'l> = recv.into();
+    let (success, failure) = if is_option {
+      ("Some", "None")
+    } else {
+      ("Ok", "Err")
+    };
+    let is_success = models.is(r, is_option, success);
+    let value = models.get(r, is_option, success);
+    let bool_tpe: st::Type<'l> = f.BooleanType().into();
+
+    let parts = match item {
+      OptionIsSomeFn | OptionIsNoneFn | ResultIsOkFn | ResultIsErrFn => {
+        let body = match item {
+          OptionIsSomeFn | ResultIsOkFn => is_success,
+          _ => models.is(r, is_option, failure),
+        };
+        ModelFnParts {
+          params: vec![],
+          return_tpe: bool_tpe,
+          pre: None,
+          body,
+          post: None,
+        }
+      }
+
+      // Unwrapping requires the value to be present
+      OptionUnwrapFn | ResultUnwrapFn => ModelFnParts {
+        params: vec![],
+        return_tpe: tps[0],
+        pre: Some(is_success),
+        body: value,
+        post: None,
+      },
+
+      OptionUnwrapOrFn | ResultUnwrapOrFn => {
+        let default = self.model_var(&[owner, name, "default"], tps[0]);
+        ModelFnParts {
+          params: vec![f.ValDef(default)],
+          return_tpe: tps[0],
+          pre: None,
+          body: f.IfExpr(is_success, value, default.into()).into(),
+          post: None,
+        }
+      }
+
+      OptionMapFn | ResultMapFn => {
+        let fun_tpe = f.FunctionType(vec![tps[0]], tps[tps.len() - 1]).into();
+        let fun = self.model_var(&[owner, name, "f"], fun_tpe);
+        let applied = f.Application(fun.into(), vec![value]).into();
+        let (return_tpe, thenn, elze) = if is_option {
+          (
+            models.option_type(tps[1]),
+            models.make(true, "Some", vec![tps[1]], vec![applied]),
+            models.make(true, "None", vec![tps[1]], vec![]),
+          )
+        } else {
+          let error = models.get(r, false, "Err");
+          (
+            models.result_type(tps[2], tps[1]),
+            models.make(false, "Ok", vec![tps[2], tps[1]], vec![applied]),
+            models.make(false, "Err", vec![tps[2], tps[1]], vec![error]),
+          )
+        };
+        let res = self.model_var(&[owner, name, "res"], return_tpe);
+        ModelFnParts {
+          params: vec![f.ValDef(fun)],
+          return_tpe,
+          pre: None,
+          body: f.IfExpr(is_success, thenn, elze).into(),
+          post: Some((
+            res,
+            f.Equals(models.is(res.into(), is_option, success), is_success)
+              .into(),
+          )),
+        }
+      }
+
+      ResultMapErrFn => {
+        let fun_tpe = f.FunctionType(vec![tps[1]], tps[2]).into();
+        let fun = self.model_var(&[owner, name, "f"], fun_tpe);
+        let error = models.get(r, false, "Err");
+        let applied = f.Application(fun.into(), vec![error]).into();
+        let return_tpe = models.result_type(tps[0], tps[2]);
+        let res = self.model_var(&[owner, name, "res"], return_tpe);
+        ModelFnParts {
+          params: vec![f.ValDef(fun)],
+          return_tpe,
+          pre: None,
+          body: f
+            .IfExpr(
+              is_success,
+              models.make(false, "Ok", vec![tps[0], tps[2]], vec![value]),
+              models.make(false, "Err", vec![tps[0], tps[2]], vec![applied]),
+            )
+            .into(),
+          post: Some((
+            res,
+            f.Equals(models.is(res.into(), false, "Ok"), is_success)
+              .into(),
+          )),
+        }
+      }
+
+      OptionAndThenFn | ResultAndThenFn => {
+        let return_tpe = if is_option {
+          models.option_type(tps[1])
+        } else {
+          models.result_type(tps[2], tps[1])
+        };
+        let fun_tpe = f.FunctionType(vec![tps[0]], return_tpe).into();
+        let fun = self.model_var(&[owner, name, "f"], fun_tpe);
+        let applied = f.Application(fun.into(), vec![value]).into();
+        let elze = if is_option {
+          models.make(true, "None", vec![tps[1]], vec![])
+        } else {
+          let error = models.get(r, false, "Err");
+          models.make(false, "Err", vec![tps[2], tps[1]], vec![error])
+        };
+        let res = self.model_var(&[owner, name, "res"], return_tpe);
+        ModelFnParts {
+          params: vec![f.ValDef(fun)],
+          return_tpe,
+          pre: None,
+          body: f.IfExpr(is_success, applied, elze).into(),
+          post: Some((
+            res,
+            f.Implies(models.is(res.into(), is_option, success), is_success)
+              .into(),
+          )),
+        }
+      }
+
+      OptionOrFn => {
+        let other = self.model_var(&[owner, name, "other"], recv_tpe);
+        let res = self.model_var(&[owner, name, "res"], recv_tpe);
+        let other_is_some = models.is(other.into(), true, "Some");
+        ModelFnParts {
+          params: vec![f.ValDef(other)],
+          return_tpe: recv_tpe,
+          pre: None,
+          body: f.IfExpr(is_success, r, other.into()).into(),
+          post: Some((
+            res,
+            f.Equals(
+              models.is(res.into(), true, "Some"),
+              f.Or(vec![is_success, other_is_some]).into(),
+            )
+            .into(),
+          )),
+        }
+      }
+
+      OptionFilterFn => {
+        let pred_tpe = f.FunctionType(vec![tps[0]], bool_tpe).into();
+        let pred = self.model_var(&[owner, name, "p"], pred_tpe);
+        let res = self.model_var(&[owner, name, "res"], recv_tpe);
+        let holds = f.Application(pred.into(), vec![value]).into();
+        ModelFnParts {
+          params: vec![f.ValDef(pred)],
+          return_tpe: recv_tpe,
+          pre: None,
+          body: f
+            .IfExpr(
+              f.And(vec![is_success, holds]).into(),
+              r,
+              models.make(true, "None", vec![tps[0]], vec![]),
+            )
+            .into(),
+          post: Some((
+            res,
+            f.Implies(models.is(res.into(), true, "Some"), is_success)
+              .into(),
+          )),
+        }
+      }
+
+      OptionOkOrFn | ResultOkFn | ResultErrFn => {
+        let (params, return_tpe, body) = match item {
+          OptionOkOrFn => {
+            let error = self.model_var(&[owner, name, "err"], tps[1]);
+            let body = f.IfExpr(
+              is_success,
+              models.make(false, "Ok", tps.clone(), vec![value]),
+              models.make(false, "Err", tps.clone(), vec![error.into()]),
+            );
+            let return_tpe = models.result_type(tps[0], tps[1]);
+            (vec![&*f.ValDef(error)], return_tpe, body)
+          }
+          ResultOkFn => {
+            let body = f.IfExpr(
+              is_success,
+              models.make(true, "Some", vec![tps[0]], vec![value]),
+              models.make(true, "None", vec![tps[0]], vec![]),
+            );
+            (vec![], models.option_type(tps[0]), body)
+          }
+          _ => {
+            let error = models.get(r, false, "Err");
+            let body = f.IfExpr(
+              is_success,
+              models.make(true, "None", vec![tps[1]], vec![]),
+              models.make(true, "Some", vec![tps[1]], vec![error]),
+            );
+            (vec![], models.option_type(tps[1]), body)
+          }
+        };
+        let res = self.model_var(&[owner, name, "res"], return_tpe);
+        let res_is_success = models.is(
+          res.into(),
+          !is_option,
+          if is_option { "Ok" } else { "Some" },
+        );
+        let res_is_success: st::Expr<'l> = match item {
+          ResultErrFn => f.Not(res_is_success).into(),
+          _ => res_is_success,
+        };
+        ModelFnParts {
+          params,
+          return_tpe,
+          pre: None,
+          body: body.into(),
+          post: Some((res, f.Equals(res_is_success, is_success).into())),
+        }
+      }
+
+      _ => unreachable!(),
+    };
+
+    let ModelFnParts {
+      params,
+      return_tpe,
+      pre,
+      mut body,
+      post,
+    } = parts;
+    if let Some(pre) = pre {
+      body = f.Require(pre, body).into();
+    }
+    if let Some((res, post)) = post {
+      body = f.Ensuring(body, f.Lambda(vec![f.ValDef(res)], post)).into();
+    }
+
+    let fun_id = match library_method(item) {
+      Some(method) => self.library_model_id(name, &[models.library_name(is_option), method]),
+      None => self.model_id(&[owner, name]),
+    };
+    self.with_extraction_mut(|xt| xt.mapping.did_to_stid.insert(def_id, fun_id));
+    let tparams = tparams
+      .into_iter()
+      .map(|tparam| &*f.TypeParameterDef(tparam))
+      .collect();
+    let params = std::iter::once(&*f.ValDef(recv)).chain(params).collect();
+    let fd = f.FunDef(
+      fun_id,
+      tparams,
+      params,
+      return_tpe,
+      body,
+      vec![f.Synthetic().into()],
+    );
+    self.add_function(fun_id, fd);
+    fd
+  }
+}
+
+impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
+  /// Extract a call to a method of `Option` or `Result` as a call to its model function.
+  /// Arguments beyond those of the model function, like the message of `expect`, are dropped.
+  pub(super) fn extract_model_call(
+    &mut self,
+    item: StdItem,
+    substs: SubstsRef<'tcx>,
+    args: &[ExprRef<'tcx>],
+    span: Span,
+  ) -> st::Expr<'l> {
+    // References are erased, so `as_ref` is the identity
+    if let OptionAsRefFn | ResultAsRefFn = item {
+      return self.extract_expr_ref(args[0].clone());
+    }
+
+    // `expect` only differs from `unwrap` by its message, which we drop
+    let item = match item {
+      OptionExpectFn => OptionUnwrapFn,
+      ResultExpectFn => ResultUnwrapFn,
+      _ => item,
+    };
+    let fd = self.base.extract_model_fn(item);
+    let tps = self
+      .base
+      .extract_tys(substs.types().take(fd.tparams.len()), &self.txtcx, span);
+    let args = self.extract_expr_refs(args.iter().take(fd.params.len()).cloned().collect());
+    self.factory().FunctionInvocation(fd.id, tps, args).into()
+  }
+}
@@ -0,0 +1,584 @@
+use super::*;
+
+use rustc_hair::hair::{
+  Arm, Block, BlockSafety, Expr, ExprKind, ExprRef, Guard, PatKind, StmtKind, StmtRef,
+};
+use rustc_hir::{MatchSource, Node};
+use rustc_middle::ty::{Ty, TyKind};
+use rustc_span::hygiene::DesugaringKind;
+use rustc_span::symbol::sym;
+
+use stainless_data::ast as st;
+
+// Extraction of early exits, i.e. `return` and `?`.
+//
+// Stainless has no notion of exiting a function early, so we restructure function bodies
+// instead. Expressions that may exit early are extracted along with their continuation, i.e.
+// the expression computing the rest of the function body from their value, which we only place
+// on the paths that don't exit. For instance,
+//   if x < 0 { return None; }
+//   Some(x)
+// becomes `if (x < 0) None() else Some(x)`. The `?` operator is extracted in the same way, as a
+// match on the `Option` or `Result` whose failure case exits early.
+//
+// To avoid duplicating continuations, all but one of the branches of an `if` or `match` that
+// contains early exits have to diverge. Beyond branches, blocks and `let`s, we only support `?`
+// in positions that are always evaluated, where we hoist it out of the surrounding expression.
+
+type Cont<'a, 'l, 'tcx> =
+  Box<dyn FnOnce(&mut BodyExtractor<'a, 'l, 'tcx>, st::Expr<'l>) -> st::Expr<'l> + 'l>;
+
+impl<'a, 'l, 'tcx> BodyExtractor<'a, 'l, 'tcx> {
+  /// Extract the body of a function, restructuring it around any early exits it contains.
+  pub(super) fn extract_body_expr(&mut self, expr: Expr<'tcx>) -> st::Expr<'l> {
+    let mut collector = EarlyExitCollector { exits: vec![] };
+    collector.visit_expr(&self.body.value);
+    self.early_exits = collector.exits;
+
+    if self.early_exits.is_empty() {
+      self.extract_expr(expr)
+    } else {
+      self.extract_with_cont(expr, Box::new(|_, expr| expr))
+    }
+  }
+
+  fn may_exit_early(&self, span: Span) -> bool {
+    self.early_exits.iter().any(|exit| span.contains(exit.span))
+  }
+
+  fn extract_with_cont(&mut self, expr: Expr<'tcx>, cont: Cont<'a, 'l, 'tcx>) -> st::Expr<'l> {
+    if !self.may_exit_early(expr.span) {
+      let expr = self.extract_expr(expr);
+      return cont(self, expr);
+    }
+
+    let span = expr.span;
+    match expr.kind {
+      // The value returned replaces the rest of the function body
+      ExprKind::Return { value } => match value {
+        Some(value) => {
+          let value = self.mirror(value);
+          self.extract_with_cont(value, Box::new(|_, value| value))
+        }
+        None => self.factory().UnitLiteral().into(),
+      },
+
+      ExprKind::Scope {
+        value,
+        region_scope,
+        ..
+      } => {
+        let value = self.mirror(value);
+        match value.kind {
+          ExprKind::Loop { .. } => {
+            let expr = self.extract_loop(value, Some(region_scope));
+            cont(self, expr)
+          }
+          _ => self.extract_with_cont(value, cont),
+        }
+      }
+
+      // References are erased, so we can look right through borrows and dereferences
+      ExprKind::Use { source: value }
+      | ExprKind::NeverToAny { source: value }
+      | ExprKind::Borrow { arg: value, .. }
+      | ExprKind::Deref { arg: value } => {
+        let value = self.mirror(value);
+        self.extract_with_cont(value, cont)
+      }
+
+      ExprKind::Block { body: ast_block } => {
+        let Block {
+          mut stmts,
+          expr: final_expr,
+          safety_mode,
+          ..
+        } = self.mirror(ast_block);
+        match safety_mode {
+          BlockSafety::Safe => {
+            stmts.reverse();
+            self.extract_stmts_with_cont(stmts, final_expr, cont)
+          }
+          _ => self.unsupported_expr(span, "Cannot extract unsafe block"),
+        }
+      }
+
+      ExprKind::Match { scrutinee, arms } => {
+        if span.is_desugaring(DesugaringKind::QuestionMark) {
+          return self.extract_try_with_cont(scrutinee, arms, span, cont);
+        }
+        if self.looks_like_for_loop(scrutinee.clone(), &arms) {
+          let expr = self.extract_for_loop(scrutinee, arms, span);
+          return cont(self, expr);
+        }
+
+        let is_if = self.looks_like_if(scrutinee.clone(), &arms).is_some();
+        let scrutinee = self.mirror(scrutinee);
+        self.extract_with_cont(
+          scrutinee,
+          Box::new(move |this, scrutinee| {
+            this.extract_branches_with_cont(scrutinee, arms, is_if, span, cont)
+          }),
+        )
+      }
+
+      kind => {
+        let expr = Expr { kind, ..expr };
+        let mut tries = self.hoistable_tries(&expr);
+        tries.reverse();
+        self.extract_hoisting_tries(expr, tries, cont)
+      }
+    }
+  }
+
+  /// Extract the statements of a block, followed by its final expression.
+  /// The statements are given in reverse order.
+  fn extract_stmts_with_cont(
+    &mut self,
+    mut stmts: Vec<StmtRef<'tcx>>,
+    final_expr: Option<ExprRef<'tcx>>,
+    cont: Cont<'a, 'l, 'tcx>,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    let stmt = match stmts.pop() {
+      Some(stmt) => self.mirror(stmt),
+      None => {
+        return match final_expr {
+          Some(final_expr) => {
+            let final_expr = self.mirror(final_expr);
+            self.extract_with_cont(final_expr, cont)
+          }
+          None => cont(self, f.UnitLiteral().into()),
+        };
+      }
+    };
+
+    match stmt.kind {
+      StmtKind::Expr { expr, .. } => {
+        let expr = self.mirror(expr);
+        self.extract_with_cont(
+          expr,
+          Box::new(move |this, expr| {
+            let rest = this.extract_stmts_with_cont(stmts, final_expr, cont);
+            match expr {
+              st::Expr::UnitLiteral(_) => rest,
+              // Assertions and assumptions hold in the rest of the block
+              st::Expr::Assert(st::Assert {
+                pred,
+                error,
+                body: st::Expr::UnitLiteral(_),
+              }) => f.Assert(*pred, error.clone(), rest).into(),
+              st::Expr::Assume(st::Assume {
+                pred,
+                body: st::Expr::UnitLiteral(_),
+              }) => f.Assume(*pred, rest).into(),
+              _ => f.Block(vec![expr], rest).into(),
+            }
+          }),
+        )
+      }
+
+      StmtKind::Let {
+        pattern,
+        initializer,
+        ..
+      } => match (self.try_pattern_to_var(&pattern.kind, false), initializer) {
+        (Ok((var, is_mutable)), Some(init)) => {
+          let init = self.mirror(init);
+          self.extract_with_cont(
+            init,
+            Box::new(move |this, init| {
+              let rest = this.extract_stmts_with_cont(stmts, final_expr, cont);
+              let vd = f.ValDef(var);
+              if is_mutable {
+                f.LetVar(vd, init, rest).into()
+              } else {
+                f.Let(vd, init, rest).into()
+              }
+            }),
+          )
+        }
+        (Err(reason), _) => self.unsupported_expr(
+          pattern.span,
+          format!("Cannot extract complex pattern in let: {}", reason),
+        ),
+        (_, None) => self.unsupported_expr(pattern.span, "Cannot extract let without initializer"),
+      },
+    }
+  }
+
+  /// Extract the branches of an `if` or a `match`, placing the continuation on the one branch
+  /// that falls through, if any of the branches exit early.
+  fn extract_branches_with_cont(
+    &mut self,
+    scrutinee: st::Expr<'l>,
+    arms: Vec<Arm<'tcx>>,
+    is_if: bool,
+    span: Span,
+    cont: Cont<'a, 'l, 'tcx>,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    let bodies: Vec<Expr<'tcx>> = arms
+      .iter()
+      .map(|arm| self.mirror(arm.body.clone()))
+      .collect();
+
+    // Only the branch falling through is followed by the continuation, the others diverge
+    let (bodies, cont): (Vec<st::Expr<'l>>, _) =
+      if bodies.iter().any(|body| self.may_exit_early(body.span)) {
+        let diverging: Vec<bool> = bodies.iter().map(|body| self.diverges(body)).collect();
+        if diverging.iter().filter(|&&diverges| !diverges).count() > 1 {
+          let msg = "Cannot extract early exits from more than one branch that may fall through";
+          return self.unsupported_expr(span, msg);
+        }
+
+        let mut cont = Some(cont);
+        let bodies = bodies
+          .into_iter()
+          .zip(diverging)
+          .map(|(body, diverges)| {
+            let cont: Cont<'a, 'l, 'tcx> = if diverges {
+              Box::new(|_, body| body)
+            } else {
+              cont.take().unwrap()
+            };
+            self.extract_with_cont(body, cont)
+          })
+          .collect();
+        (bodies, None)
+      } else {
+        let bodies = bodies
+          .into_iter()
+          .map(|body| self.extract_expr(body))
+          .collect();
+        (bodies, Some(cont))
+      };
+
+    let expr = if is_if {
+      f.IfExpr(scrutinee, bodies[0], bodies[1]).into()
+    } else {
+      let cases = arms
+        .into_iter()
+        .zip(bodies)
+        .map(|(arm, body)| {
+          let pattern = self.extract_pattern(arm.pattern, None);
+          let guard = arm.guard.map(|Guard::If(expr)| self.extract_expr_ref(expr));
+          &*f.MatchCase(pattern, guard, body)
+        })
+        .collect();
+      f.MatchExpr(scrutinee, cases).into()
+    };
+
+    // If none of the branches exit early, the entire expression is followed by the continuation
+    match cont {
+      Some(cont) => cont(self, expr),
+      None => expr,
+    }
+  }
+
+  /// Extract `e?` as a match on `e`, which continues with the success value, or returns the
+  /// failure. `?` desugars to a match on `Try::into_result(e)`, whose first arm binds the success
+  /// value, and whose second one binds the error.
+  fn extract_try_with_cont(
+    &mut self,
+    scrutinee: ExprRef<'tcx>,
+    arms: Vec<Arm<'tcx>>,
+    span: Span,
+    cont: Cont<'a, 'l, 'tcx>,
+  ) -> st::Expr<'l> {
+    let scrutinee = self.mirror(scrutinee);
+    let arg = match self.strip_scopes(scrutinee).kind {
+      ExprKind::Call { mut args, .. } if args.len() == 1 => args.pop().unwrap(),
+      _ => unexpected(span, "Expected call to `Try::into_result`"),
+    };
+    let arg = self.mirror(arg);
+    let ty = arg.ty;
+    let value_var = self.fetch_arm_binding(&arms[0]);
+    let error_var = self.fetch_arm_binding(&arms[1]);
+
+    self.extract_with_cont(
+      arg,
+      Box::new(move |this, scrutinee| {
+        this.extract_try_match(scrutinee, ty, value_var, error_var, span, cont)
+      }),
+    )
+  }
+
+  fn extract_try_match(
+    &mut self,
+    scrutinee: st::Expr<'l>,
+    ty: Ty<'tcx>,
+    value_var: &'l st::Variable<'l>,
+    error_var: &'l st::Variable<'l>,
+    span: Span,
+    cont: Cont<'a, 'l, 'tcx>,
+  ) -> st::Expr<'l> {
+    let f = self.factory();
+    let tcx = self.tcx();
+    let return_ty = self.return_ty();
+
+    let (adt_def, substs, return_substs) = match (&ty.kind, &return_ty.kind) {
+      (TyKind::Adt(adt_def, substs), TyKind::Adt(return_adt_def, return_substs))
+        if adt_def == return_adt_def =>
+      {
+        (*adt_def, *substs, *return_substs)
+      }
+      _ => {
+        return self.unsupported_expr(
+          span,
+          "Can only extract `?` on an `Option` or `Result` in functions returning the same",
+        )
+      }
+    };
+
+    let is_option = tcx.is_diagnostic_item(sym::option_type, adt_def.did);
+    let is_result = tcx.is_diagnostic_item(sym::result_type, adt_def.did);
+    if !is_option && !is_result {
+      return self.unsupported_expr(span, "Can only extract `?` on an `Option` or `Result`");
+    }
+    if is_result
+      && tcx.erase_regions(&substs.type_at(1)) != tcx.erase_regions(&return_substs.type_at(1))
+    {
+      return self.unsupported_expr(span, "Cannot extract `?` converting between error types");
+    }
+
+    let sort = self.base.extract_adt(adt_def.did);
+    let constructor_id = |name: &str| {
+      let index = adt_def
+        .variants
+        .iter()
+        .position(|variant| variant.ident.as_str() == name)
+        .unwrap();
+      sort.constructors[index].id
+    };
+    let (success_name, failure_name) = if is_option {
+      ("Some", "None")
+    } else {
+      ("Ok", "Err")
+    };
+    let (success_id, failure_id) = (constructor_id(success_name), constructor_id(failure_name));
+    let tps = self.extract_arg_types(substs, span);
+    let return_tps = self.extract_arg_types(return_substs, span);
+
+    let success_pattern = f.ADTPattern(
+      None,
+      success_id,
+      tps.clone(),
+      vec![f.WildcardPattern(Some(f.ValDef(value_var))).into()],
+    );
+    let (failure_pattern, failure_expr) = if is_option {
+      (
+        f.ADTPattern(None, failure_id, tps, vec![]),
+        f.ADT(failure_id, return_tps, vec![]),
+      )
+    } else {
+      (
+        f.ADTPattern(
+          None,
+          failure_id,
+          tps,
+          vec![f.WildcardPattern(Some(f.ValDef(error_var))).into()],
+        ),
+        f.ADT(failure_id, return_tps, vec![error_var.into()]),
+      )
+    };
+
+    let success_expr = cont(self, value_var.into());
+    let cases = vec![
+      &*f.MatchCase(success_pattern.into(), None, success_expr),
+      &*f.MatchCase(failure_pattern.into(), None, failure_expr.into()),
+    ];
+    f.MatchExpr(scrutinee, cases).into()
+  }
+
+  /// Whether the given expression never evaluates to a value. Note that blocks ending in a
+  /// `return` statement are typed as `()` when they form the `then` branch of an `if`.
+  fn diverges(&mut self, expr: &Expr<'tcx>) -> bool {
+    if expr.ty.is_never() {
+      return true;
+    }
+    match &expr.kind {
+      ExprKind::NeverToAny { .. } | ExprKind::Return { .. } => true,
+      ExprKind::Scope { value, .. } => {
+        let value = self.mirror(value.clone());
+        self.diverges(&value)
+      }
+      ExprKind::Block { body: ast_block } => {
+        let Block { stmts, expr, .. } = self.mirror(*ast_block);
+        let diverging_stmt = stmts.into_iter().any(|stmt| match self.mirror(stmt).kind {
+          StmtKind::Expr { expr, .. } => self.mirror(expr).ty.is_never(),
+          StmtKind::Let {
+            initializer: Some(init),
+            ..
+          } => self.mirror(init).ty.is_never(),
+          _ => false,
+        });
+        diverging_stmt
+          || expr.map_or(false, |expr| {
+            let expr = self.mirror(expr);
+            self.diverges(&expr)
+          })
+      }
+      _ => false,
+    }
+  }
+
+  /// Fetch the variable bound by the single-field pattern of an arm introduced by `?`.
+  fn fetch_arm_binding(&self, arm: &Arm<'tcx>) -> &'l st::Variable<'l> {
+    match &*arm.pattern.kind {
+      PatKind::Variant { subpatterns, .. } | PatKind::Leaf { subpatterns } => {
+        match &*subpatterns[0].pattern.kind {
+          PatKind::Binding { var, .. } => self.fetch_var(*var),
+          _ => unexpected(arm.span, "Expected binding in arm of `?`"),
+        }
+      }
+      _ => unexpected(arm.span, "Expected variant pattern in arm of `?`"),
+    }
+  }
+
+  fn return_ty(&self) -> Ty<'tcx> {
+    let hir_id = self.tcx().hir().body_owner(self.body.id());
+    self.tables.liberated_fn_sigs()[hir_id].output()
+  }
+
+  /// Extract an expression after hoisting the given `?`s out of it, in reverse order. Their
+  /// values are recorded in `try_values`, where the extraction of the `?`s picks them up again.
+  fn extract_hoisting_tries(
+    &mut self,
+    expr: Expr<'tcx>,
+    mut tries: Vec<&'tcx hir::Expr<'tcx>>,
+    cont: Cont<'a, 'l, 'tcx>,
+  ) -> st::Expr<'l> {
+    match tries.pop() {
+      Some(try_expr) => {
+        let try_span = try_expr.span;
+        let try_expr = self.mirror_try(try_expr);
+        self.extract_with_cont(
+          try_expr,
+          Box::new(move |this, value| {
+            this.try_values.insert(try_span, value);
+            this.extract_hoisting_tries(expr, tries, cont)
+          }),
+        )
+      }
+      None => {
+        let expr = self.extract_expr(expr);
+        cont(self, expr)
+      }
+    }
+  }
+
+  /// The outermost `?`s in the given expression in evaluation order, if all of them can be
+  /// hoisted out of it. Other early exits are reported once we extract the expression.
+  fn hoistable_tries(&self, expr: &Expr<'tcx>) -> Vec<&'tcx hir::Expr<'tcx>> {
+    let exits: Vec<&'tcx hir::Expr<'tcx>> = self
+      .early_exits
+      .iter()
+      .copied()
+      .filter(|exit| expr.span.contains(exit.span))
+      .collect();
+    let can_hoist = exits
+      .iter()
+      .all(|exit| is_try(exit) && self.is_evaluated_strictly(exit, expr.span));
+    if !can_hoist {
+      return vec![];
+    }
+
+    let mut tries: Vec<&'tcx hir::Expr<'tcx>> = exits
+      .iter()
+      .copied()
+      .filter(|exit| {
+        !exits
+          .iter()
+          .any(|other| other.hir_id != exit.hir_id && other.span.contains(exit.span))
+      })
+      .collect();
+    tries.sort_by_key(|exit| exit.span.lo());
+    tries
+  }
+
+  /// Whether the given subexpression is evaluated whenever the expression spanning `within` is.
+  fn is_evaluated_strictly(&self, expr: &'tcx hir::Expr<'tcx>, within: Span) -> bool {
+    let hir = self.tcx().hir();
+    let mut child = expr.hir_id;
+    loop {
+      let parent = hir.get_parent_node(child);
+      if parent == child || !within.contains(hir.span(parent)) {
+        return true;
+      }
+      match hir.get(parent) {
+        Node::Expr(hir::Expr { kind, .. }) => match kind {
+          hir::ExprKind::Match(scrutinee, ..) if scrutinee.hir_id != child => return false,
+          hir::ExprKind::Binary(op, _, rhs) if op.node.is_lazy() && rhs.hir_id == child => {
+            return false
+          }
+          hir::ExprKind::Loop(..) | hir::ExprKind::Closure(..) => return false,
+          _ => {}
+        },
+        Node::Arm(_) => return false,
+        _ => {}
+      }
+      child = parent;
+    }
+  }
+
+  /// Mirror a `?`, looking through the adjustments applied to it.
+  fn mirror_try(&mut self, hir_expr: &'tcx hir::Expr<'tcx>) -> Expr<'tcx> {
+    let mut expr = self.mirror(hir_expr);
+    loop {
+      expr = match expr.kind {
+        ExprKind::Match { .. } if expr.span == hir_expr.span => return expr,
+        ExprKind::Scope { value, .. }
+        | ExprKind::Use { source: value }
+        | ExprKind::NeverToAny { source: value }
+        | ExprKind::Pointer { source: value, .. }
+        | ExprKind::Borrow { arg: value, .. }
+        | ExprKind::Deref { arg: value } => self.mirror(value),
+        _ => unexpected(hir_expr.span, "Expected `?`"),
+      }
+    }
+  }
+
+  /// Extract a `?` whose value was hoisted out of the surrounding expression.
+  pub(super) fn extract_hoisted_try(&mut self, span: Span) -> st::Expr<'l> {
+    match self.try_values.remove(&span) {
+      Some(value) => value,
+      None => self.unsupported_expr(span, "Cannot extract `?` in this position"),
+    }
+  }
+}
+
+fn is_try(expr: &hir::Expr<'_>) -> bool {
+  matches!(
+    expr.kind,
+    hir::ExprKind::Match(_, _, MatchSource::TryDesugar)
+  )
+}
+
+/// EarlyExitCollector finds all `return`s and `?`s of a body, without entering closures
+struct EarlyExitCollector<'tcx> {
+  exits: Vec<&'tcx hir::Expr<'tcx>>,
+}
+
+impl<'tcx> Visitor<'tcx> for EarlyExitCollector<'tcx> {
+  type Map = rustc_middle::hir::map::Map<'tcx>;
+
+  fn nested_visit_map(&mut self) -> NestedVisitorMap<Self::Map> {
+    NestedVisitorMap::None
+  }
+
+  fn visit_expr(&mut self, expr: &'tcx hir::Expr<'tcx>) {
+    match expr.kind {
+      // The `return` in the desugaring of `?` is part of the `?` itself
+      hir::ExprKind::Match(scrutinee, _, MatchSource::TryDesugar) => {
+        self.exits.push(expr);
+        self.visit_expr(scrutinee);
+      }
+      hir::ExprKind::Ret(_) => {
+        self.exits.push(expr);
+        intravisit::walk_expr(self, expr);
+      }
+      _ => intravisit::walk_expr(self, expr),
+    }
+  }
+}
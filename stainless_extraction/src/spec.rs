@@ -8,6 +8,7 @@ pub enum SpecType {
   Pre,
   Post,
   Measure,
+  Invariant,
 }
 
 impl SpecType {
@@ -38,6 +39,7 @@ impl TryFrom<&str> for SpecType {
       "pre" => Ok(SpecType::Pre),
       "post" => Ok(SpecType::Post),
       "measure" => Ok(SpecType::Measure),
+      "invariant" => Ok(SpecType::Invariant),
       _ => Err(()),
     }
   }
@@ -65,6 +67,10 @@ mod tests {
       SpecType::parse_spec_type_fn_name("__measure_2_dummy_for_specs_2"),
       Some((SpecType::Measure, "dummy_for_specs_2".to_string()))
     );
+    assert_eq!(
+      SpecType::parse_spec_type_fn_name("__invariant_1_Positive"),
+      Some((SpecType::Invariant, "Positive".to_string()))
+    );
 
     assert_eq!(
       SpecType::parse_spec_type_fn_name("__measure_bcdf_asdf"),
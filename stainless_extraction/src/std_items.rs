@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use rustc_hir::def_id::{CrateId, CrateNum, DefId, DefIndex};
 use rustc_hir::lang_items::*;
 use rustc_middle::ty::TyCtxt;
-use rustc_span::symbol::Symbol;
+use rustc_span::symbol::{sym, Symbol};
 
 /// A standard item, either a rust LangItem, or one of the stainless library
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -12,8 +12,24 @@ pub(super) enum StdItem {
   FnMutTrait,
   FnOnceTrait,
   SizedTrait,
+  CloneTrait,
+  AddTrait,
+  SubTrait,
+  MulTrait,
+  DivTrait,
+  RemTrait,
+  NegTrait,
+  PartialEqTrait,
+  PartialOrdTrait,
+  FromTrait,
+  IntoTrait,
+  WrappingType,
   BeginPanicFn,
   BeginPanicFmtFn,
+  // Range things
+  RangeType,
+  RangeInclusiveType,
+  RangeInclusiveNewFn,
   // Set things,
   SetType,
   SetAddFn,
@@ -23,9 +39,67 @@ pub(super) enum StdItem {
   SubsetOfFn,
   SetEmptyFn,
   SetSingletonFn,
+  // Loop things
+  InvariantFn,
+  // Spec things
+  OldFn,
+  ForallFn,
+  ExistsFn,
+  AssumeFn,
+  // Option things
+  OptionType,
+  OptionIsSomeFn,
+  OptionIsNoneFn,
+  OptionUnwrapFn,
+  OptionExpectFn,
+  OptionUnwrapOrFn,
+  OptionMapFn,
+  OptionAndThenFn,
+  OptionOrFn,
+  OptionFilterFn,
+  OptionOkOrFn,
+  OptionAsRefFn,
+  // Result things
+  ResultType,
+  ResultIsOkFn,
+  ResultIsErrFn,
+  ResultOkFn,
+  ResultErrFn,
+  ResultUnwrapFn,
+  ResultExpectFn,
+  ResultUnwrapOrFn,
+  ResultMapFn,
+  ResultMapErrFn,
+  ResultAndThenFn,
+  ResultAsRefFn,
 }
 
-const RUST_LANG_ITEMS: &[StdItem] = &[FnTrait, FnMutTrait, FnOnceTrait, SizedTrait, BeginPanicFn];
+const RUST_LANG_ITEMS: &[StdItem] = &[
+  FnTrait,
+  FnMutTrait,
+  FnOnceTrait,
+  SizedTrait,
+  CloneTrait,
+  AddTrait,
+  SubTrait,
+  MulTrait,
+  DivTrait,
+  RemTrait,
+  NegTrait,
+  PartialEqTrait,
+  PartialOrdTrait,
+  BeginPanicFn,
+];
+
+// Items of core that are neither lang items nor diagnostic items in our version of rustc, and are
+// found by their def paths instead
+const CORE_ITEMS: &[StdItem] = &[
+  RangeType,
+  RangeInclusiveType,
+  FromTrait,
+  IntoTrait,
+  WrappingType,
+];
 
 const STAINLESS_ITEMS: &[StdItem] = &[
   SetType,
@@ -36,11 +110,44 @@ const STAINLESS_ITEMS: &[StdItem] = &[
   SubsetOfFn,
   SetEmptyFn,
   SetSingletonFn,
+  InvariantFn,
+  OldFn,
+  ForallFn,
+  ExistsFn,
+  AssumeFn,
+];
+
+const OPTION_ITEMS: &[StdItem] = &[
+  OptionIsSomeFn,
+  OptionIsNoneFn,
+  OptionUnwrapFn,
+  OptionExpectFn,
+  OptionUnwrapOrFn,
+  OptionMapFn,
+  OptionAndThenFn,
+  OptionOrFn,
+  OptionFilterFn,
+  OptionOkOrFn,
+  OptionAsRefFn,
+];
+
+const RESULT_ITEMS: &[StdItem] = &[
+  ResultIsOkFn,
+  ResultIsErrFn,
+  ResultOkFn,
+  ResultErrFn,
+  ResultUnwrapFn,
+  ResultExpectFn,
+  ResultUnwrapOrFn,
+  ResultMapFn,
+  ResultMapErrFn,
+  ResultAndThenFn,
+  ResultAsRefFn,
 ];
 
 use StdItem::*;
 
-const NUM_STD_ITEMS: usize = 14;
+const NUM_STD_ITEMS: usize = 58;
 
 impl StdItem {
   fn index(self) -> usize {
@@ -58,7 +165,51 @@ impl StdItem {
       SetUnionFn => 10,
       SubsetOfFn => 11,
       SetEmptyFn => 12,
-      SetSingletonFn => NUM_STD_ITEMS - 1,
+      SetSingletonFn => 13,
+      InvariantFn => 14,
+      OldFn => 15,
+      OptionType => 16,
+      OptionIsSomeFn => 17,
+      OptionIsNoneFn => 18,
+      OptionUnwrapFn => 19,
+      OptionExpectFn => 20,
+      OptionUnwrapOrFn => 21,
+      OptionMapFn => 22,
+      OptionAndThenFn => 23,
+      OptionOrFn => 24,
+      OptionFilterFn => 25,
+      OptionOkOrFn => 26,
+      OptionAsRefFn => 27,
+      ResultType => 28,
+      ResultIsOkFn => 29,
+      ResultIsErrFn => 30,
+      ResultOkFn => 31,
+      ResultErrFn => 32,
+      ResultUnwrapFn => 33,
+      ResultExpectFn => 34,
+      ResultUnwrapOrFn => 35,
+      ResultMapFn => 36,
+      ResultMapErrFn => 37,
+      ResultAndThenFn => 38,
+      ResultAsRefFn => 39,
+      CloneTrait => 40,
+      AddTrait => 41,
+      SubTrait => 42,
+      MulTrait => 43,
+      DivTrait => 44,
+      RemTrait => 45,
+      NegTrait => 46,
+      PartialEqTrait => 47,
+      PartialOrdTrait => 48,
+      ForallFn => 49,
+      ExistsFn => 50,
+      AssumeFn => 51,
+      RangeType => 52,
+      RangeInclusiveType => 53,
+      RangeInclusiveNewFn => 54,
+      FromTrait => 55,
+      IntoTrait => 56,
+      WrappingType => NUM_STD_ITEMS - 1,
     }
   }
 
@@ -68,8 +219,23 @@ impl StdItem {
       FnMutTrait => "FnMut",
       FnOnceTrait => "FnOnce",
       SizedTrait => "Sized",
+      CloneTrait => "Clone",
+      AddTrait => "Add",
+      SubTrait => "Sub",
+      MulTrait => "Mul",
+      DivTrait => "Div",
+      RemTrait => "Rem",
+      NegTrait => "Neg",
+      PartialEqTrait => "PartialEq",
+      PartialOrdTrait => "PartialOrd",
+      FromTrait => "From",
+      IntoTrait => "Into",
+      WrappingType => "Wrapping",
       BeginPanicFn => "begin_panic",
       BeginPanicFmtFn => "begin_panic_fmt",
+      RangeType => "Range",
+      RangeInclusiveType => "RangeInclusive",
+      RangeInclusiveNewFn => "new",
       SetType => "Set",
       SetAddFn => "add",
       SetDifferenceFn => "difference",
@@ -78,6 +244,51 @@ impl StdItem {
       SubsetOfFn => "is_subset_of",
       SetEmptyFn => "empty",
       SetSingletonFn => "singleton",
+      InvariantFn => "invariant",
+      OldFn => "old",
+      ForallFn => "forall",
+      ExistsFn => "exists",
+      AssumeFn => "assume_fact",
+      OptionType => "Option",
+      OptionIsSomeFn => "is_some",
+      OptionIsNoneFn => "is_none",
+      OptionUnwrapFn | ResultUnwrapFn => "unwrap",
+      OptionExpectFn | ResultExpectFn => "expect",
+      OptionUnwrapOrFn | ResultUnwrapOrFn => "unwrap_or",
+      OptionMapFn | ResultMapFn => "map",
+      OptionAndThenFn | ResultAndThenFn => "and_then",
+      OptionOrFn => "or",
+      OptionFilterFn => "filter",
+      OptionOkOrFn => "ok_or",
+      OptionAsRefFn | ResultAsRefFn => "as_ref",
+      ResultType => "Result",
+      ResultIsOkFn => "is_ok",
+      ResultIsErrFn => "is_err",
+      ResultOkFn => "ok",
+      ResultErrFn => "err",
+      ResultMapErrFn => "map_err",
+    }
+  }
+
+  /// Whether the item is a method of `Option` or `Result` that we map to a model function.
+  pub fn is_model_fn(self) -> bool {
+    OPTION_ITEMS.contains(&self) || RESULT_ITEMS.contains(&self)
+  }
+
+  /// Whether the item is a method of `Option`, as opposed to one of `Result`.
+  pub fn is_option_fn(self) -> bool {
+    OPTION_ITEMS.contains(&self)
+  }
+
+  /// The def path of an item in core, without the crate name
+  pub fn core_path(self) -> Option<&'static str> {
+    match self {
+      RangeType => Some("ops::range::Range"),
+      RangeInclusiveType => Some("ops::range::RangeInclusive"),
+      FromTrait => Some("convert::From"),
+      IntoTrait => Some("convert::Into"),
+      WrappingType => Some("num::Wrapping"),
+      _ => None,
     }
   }
 
@@ -87,6 +298,15 @@ impl StdItem {
       FnMutTrait => Some(FnMutTraitLangItem),
       FnOnceTrait => Some(FnOnceTraitLangItem),
       SizedTrait => Some(SizedTraitLangItem),
+      CloneTrait => Some(CloneTraitLangItem),
+      AddTrait => Some(AddTraitLangItem),
+      SubTrait => Some(SubTraitLangItem),
+      MulTrait => Some(MulTraitLangItem),
+      DivTrait => Some(DivTraitLangItem),
+      RemTrait => Some(RemTraitLangItem),
+      NegTrait => Some(NegTraitLangItem),
+      PartialEqTrait => Some(EqTraitLangItem),
+      PartialOrdTrait => Some(PartialOrdTraitLangItem),
       BeginPanicFn => Some(BeginPanicFnLangItem),
       _ => None,
     }
@@ -100,6 +320,15 @@ impl From<LangItem> for StdItem {
       FnMutTraitLangItem => FnMutTrait,
       FnOnceTraitLangItem => FnOnceTrait,
       SizedTraitLangItem => SizedTrait,
+      CloneTraitLangItem => CloneTrait,
+      AddTraitLangItem => AddTrait,
+      SubTraitLangItem => SubTrait,
+      MulTraitLangItem => MulTrait,
+      DivTraitLangItem => DivTrait,
+      RemTraitLangItem => RemTrait,
+      NegTraitLangItem => NegTrait,
+      EqTraitLangItem => PartialEqTrait,
+      PartialOrdTraitLangItem => PartialOrdTrait,
       BeginPanicFnLangItem => BeginPanicFn,
       _ => unimplemented!(),
     }
@@ -129,6 +358,14 @@ impl StdItems {
     // Register additional items from the rust standard library
     let std_crate_num = this.item_to_def(BeginPanicFn).krate;
     this.register_items_from_crate(tcx, &[BeginPanicFmtFn], std_crate_num);
+    this.register_items_from_adt(tcx, OptionType, sym::option_type, OPTION_ITEMS);
+    this.register_items_from_adt(tcx, ResultType, sym::result_type, RESULT_ITEMS);
+
+    // Register items of core that are neither lang items nor diagnostic items
+    let core_crate_num = this.item_to_def(SizedTrait).krate;
+    this.register_items_from_crate_paths(tcx, CORE_ITEMS, core_crate_num);
+    let range_inclusive_def_id = this.item_to_def(RangeInclusiveType);
+    this.register_inherent_items(tcx, range_inclusive_def_id, &[RangeInclusiveNewFn]);
 
     // Register items from stainless crate
     let stainless_sym = Symbol::intern("stainless");
@@ -190,6 +427,66 @@ impl StdItems {
     }
   }
 
+  /// Register items of a crate by their def paths, which unlike their names are unique.
+  fn register_items_from_crate_paths(&mut self, tcx: TyCtxt, items: &[StdItem], cnum: CrateNum) {
+    let mut items: HashMap<&str, StdItem> = items
+      .iter()
+      .map(|item| (item.core_path().unwrap(), *item))
+      .collect();
+
+    for index in 0.. {
+      if items.is_empty() {
+        break;
+      }
+      let def_id = Self::make_def_id(cnum, index);
+      let path = tcx
+        .def_path(def_id)
+        .data
+        .iter()
+        .map(|data| data.data.as_symbol().to_string())
+        .collect::<Vec<_>>()
+        .join("::");
+      if let Some(item) = items.remove(path.as_str()) {
+        self.item_to_def[item.index()] = def_id;
+        self.def_to_item.insert(def_id, item);
+      }
+    }
+  }
+
+  /// Register a diagnostic item ADT of the standard library along with its inherent methods.
+  fn register_items_from_adt(
+    &mut self,
+    tcx: TyCtxt,
+    adt_item: StdItem,
+    adt_sym: Symbol,
+    items: &[StdItem],
+  ) {
+    let adt_def_id = tcx.get_diagnostic_item(adt_sym).unwrap_or_else(|| {
+      tcx
+        .sess
+        .fatal(&format!("Couldn't find {}", adt_item.name()))
+    });
+    self.item_to_def[adt_item.index()] = adt_def_id;
+    self.def_to_item.insert(adt_def_id, adt_item);
+    self.register_inherent_items(tcx, adt_def_id, items);
+  }
+
+  /// Register inherent methods of an ADT by their names.
+  fn register_inherent_items(&mut self, tcx: TyCtxt, adt_def_id: DefId, items: &[StdItem]) {
+    let mut items: HashMap<Symbol, StdItem> = items
+      .iter()
+      .map(|item| (Symbol::intern(item.name()), *item))
+      .collect();
+    for &impl_def_id in tcx.inherent_impls(adt_def_id).iter() {
+      for assoc_item in tcx.associated_items(impl_def_id).in_definition_order() {
+        if let Some(item) = items.remove(&assoc_item.ident.name) {
+          self.item_to_def[item.index()] = assoc_item.def_id;
+          self.def_to_item.insert(assoc_item.def_id, item);
+        }
+      }
+    }
+  }
+
   #[inline]
   pub(super) fn item_to_def<I: Into<StdItem>>(&self, item: I) -> DefId {
     let item = item.into();
@@ -2,10 +2,10 @@ use super::std_items::StdItem::*;
 use super::*;
 
 use rustc_ast::ast;
-use rustc_hir::Mutability;
 use rustc_middle::ty::{
   AdtDef, GenericParamDef, GenericParamDefKind, Generics, PredicateKind, Ty, TyKind,
 };
+use rustc_span::symbol::{kw, sym};
 use rustc_span::{Span, DUMMY_SP};
 
 use stainless_data::ast as st;
@@ -49,16 +49,21 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
 
       // Integer types
       TyKind::Adt(adt_def, _) if self.is_bigint(adt_def) => f.IntegerType().into(),
+      TyKind::Adt(adt_def, substs) if self.is_wrapping(adt_def) => {
+        self.extract_ty(substs.type_at(0), txtcx, span)
+      }
       TyKind::Int(ast::IntTy::I8) => f.BVType(true, 8).into(),
       TyKind::Int(ast::IntTy::I16) => f.BVType(true, 16).into(),
       TyKind::Int(ast::IntTy::I32) => f.BVType(true, 32).into(),
       TyKind::Int(ast::IntTy::I64) => f.BVType(true, 64).into(),
       TyKind::Int(ast::IntTy::I128) => f.BVType(true, 128).into(),
+      TyKind::Int(ast::IntTy::Isize) => f.BVType(true, self.pointer_width as i32).into(),
       TyKind::Uint(ast::UintTy::U8) => f.BVType(false, 8).into(),
       TyKind::Uint(ast::UintTy::U16) => f.BVType(false, 16).into(),
       TyKind::Uint(ast::UintTy::U32) => f.BVType(false, 32).into(),
       TyKind::Uint(ast::UintTy::U64) => f.BVType(false, 64).into(),
       TyKind::Uint(ast::UintTy::U128) => f.BVType(false, 128).into(),
+      TyKind::Uint(ast::UintTy::Usize) => f.BVType(false, self.pointer_width as i32).into(),
 
       TyKind::Tuple(..) => {
         let arg_tps = self.extract_tys(ty.tuple_fields(), txtcx, span);
@@ -95,8 +100,21 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
         f.ADTType(sort_id, arg_tps).into()
       }
 
-      // Immutable references
-      TyKind::Ref(_, ty, Mutability::Not) => self.extract_ty(ty, txtcx, span),
+      // References
+      //
+      // We erase immutable references as well as mutable ones. Values of mutable types are
+      // updated in place by Stainless' imperative phase, which also rules out aliasing.
+      TyKind::Ref(_, ty, _) => self.extract_ty(ty, txtcx, span),
+
+      // Closures have the function type of their signature, whose parameters rustc tuples up
+      TyKind::Closure(_, substs) => {
+        let sig = self
+          .tcx
+          .erase_late_bound_regions(&substs.as_closure().sig());
+        let param_tps = self.extract_tys(sig.inputs()[0].tuple_fields(), txtcx, span);
+        let return_tpe = self.extract_ty(sig.output(), txtcx, span);
+        f.FunctionType(param_tps, return_tpe).into()
+      }
 
       TyKind::Param(param_ty) => txtcx
         .index_to_tparam
@@ -174,6 +192,11 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
             continue;
           }
 
+          // Bounds on user-defined traits are satisfied by passing around evidence
+          if self.is_user_trait(trait_did) {
+            continue;
+          }
+
           if let TyKind::Param(param_ty) = trait_ref.self_ty().kind {
             let param_def = generics.type_param(&param_ty, tcx);
             if self.is_fn_like_trait(trait_did) {
@@ -216,9 +239,15 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
           if tparam_to_fun_params.contains_key(&param.index) {
             None
           } else {
-            let id = self.get_or_register_def(param.def_id);
-            // TODO: Extract flags on type parameters
-            let flags = vec![];
+            let id = if param.name == kw::SelfUpper {
+              self.get_or_register_self_tparam(param.def_id)
+            } else {
+              self.get_or_register_def(param.def_id)
+            };
+            let flags = self
+              .extract_tparam_flags(param.def_id, param.name)
+              .map(|flags| flags.to_stainless(f))
+              .unwrap_or_default();
             let tparam = TyParam::Extracted(f.TypeParameter(id, flags));
             Some((param.index, tparam))
           }
@@ -256,17 +285,41 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     }
 
     // And we're done.
-    (
-      txtcx
-        .index_to_tparam
-        .values()
-        .filter_map(|tparam| match tparam {
-          TyParam::Replaced(_) => None,
-          TyParam::Extracted(tparam) => Some(&*f.TypeParameterDef(tparam)),
-        })
-        .collect(),
-      txtcx,
-    )
+    (self.tparam_defs(&txtcx, 0), txtcx)
+  }
+
+  /// The definitions of all extracted type parameters from the given index onwards, in order.
+  pub(super) fn tparam_defs(
+    &self,
+    txtcx: &TyExtractionCtxt<'l>,
+    from_index: u32,
+  ) -> Vec<&'l st::TypeParameterDef<'l>> {
+    let f = self.factory();
+    let mut tparams: Vec<(&u32, &TyParam<'l>)> = txtcx
+      .index_to_tparam
+      .iter()
+      .filter(|(index, _)| **index >= from_index)
+      .collect();
+    tparams.sort_by_key(|(index, _)| **index);
+    tparams
+      .into_iter()
+      .filter_map(|(_, tparam)| match tparam {
+        TyParam::Replaced(_) => None,
+        TyParam::Extracted(tparam) => Some(&*f.TypeParameterDef(tparam)),
+      })
+      .collect()
+  }
+
+  /// The `Self` parameter of a trait shares its DefId with the trait itself, so for local traits
+  /// we identify it by the trait's HirId instead.
+  fn get_or_register_self_tparam(&mut self, trait_def_id: DefId) -> StainlessSymId<'l> {
+    match trait_def_id.as_local() {
+      Some(local_def_id) => {
+        let hir_id = self.tcx.hir().as_local_hir_id(local_def_id);
+        self.get_or_register_hir(hir_id, "Self".into())
+      }
+      None => self.get_or_register_def(trait_def_id),
+    }
   }
 
   /// Various helpers
@@ -291,6 +344,15 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     }
   }
 
+  /// The signedness and bit width of a bitvector type
+  pub(super) fn bv_type_params(&self, ty: Ty<'tcx>) -> Option<(bool, u64)> {
+    match ty.kind {
+      TyKind::Int(int_ty) => Some((true, int_ty.bit_width().unwrap_or(self.pointer_width))),
+      TyKind::Uint(uint_ty) => Some((false, uint_ty.bit_width().unwrap_or(self.pointer_width))),
+      _ => None,
+    }
+  }
+
   pub(super) fn is_bigint_type(&self, ty: Ty<'tcx>) -> bool {
     match ty.kind {
       TyKind::Adt(adt_def, _) => self.is_bigint(adt_def),
@@ -302,6 +364,47 @@ impl<'l, 'tcx> BaseExtractor<'l, 'tcx> {
     // TODO: Add a check for BigInt that avoids generating the string?
     self.tcx.def_path_str(adt_def.did) == "num_bigint::BigInt"
   }
+
+  /// Whether `PartialEq` on the given type coincides with structural equality in Stainless,
+  /// i.e., whether it is a primitive, a reference, tuple or box of such types, or an ADT whose
+  /// `PartialEq` impl is derived and whose fields all have structural equality.
+  ///
+  /// Type parameters don't have structural equality, since their `PartialEq` impls are not
+  /// known to us. Trait bounds are looked up in the context of the item `def_id`.
+  pub(super) fn has_structural_eq(&self, def_id: DefId, ty: Ty<'tcx>) -> bool {
+    self.has_structural_eq_(def_id, ty, &mut HashSet::new())
+  }
+
+  fn has_structural_eq_(&self, def_id: DefId, ty: Ty<'tcx>, seen: &mut HashSet<DefId>) -> bool {
+    let tcx = self.tcx;
+    match ty.kind {
+      TyKind::Bool | TyKind::Char | TyKind::Int(_) | TyKind::Uint(_) => true,
+      TyKind::Ref(_, ty, _) => self.has_structural_eq_(def_id, ty, seen),
+      TyKind::Tuple(_) => ty
+        .tuple_fields()
+        .all(|ty| self.has_structural_eq_(def_id, ty, seen)),
+      TyKind::Adt(adt_def, substs) if adt_def.is_box() => {
+        self.has_structural_eq_(def_id, substs.type_at(0), seen)
+      }
+      TyKind::Adt(adt_def, _) if self.is_bigint(adt_def) => true,
+
+      // Recursive occurrences of an ADT are fine, as long as the rest of its fields are
+      TyKind::Adt(adt_def, _) if !seen.insert(adt_def.did) => true,
+      TyKind::Adt(adt_def, substs) => {
+        let partial_eq = self.std_items.item_to_def(PartialEqTrait);
+        let trait_ref = TraitRef::new(partial_eq, tcx.mk_substs_trait(ty, &[ty.into()]));
+        let is_derived = match self.select_impl(def_id, trait_ref) {
+          Some((impl_def_id, _)) => tcx.has_attr(impl_def_id, sym::automatically_derived),
+          None => false,
+        };
+        is_derived
+          && adt_def
+            .all_fields()
+            .all(|field| self.has_structural_eq_(def_id, field.ty(tcx, substs), seen))
+      }
+      _ => false,
+    }
+  }
 }
 
 // Generics helpers
@@ -14,6 +14,9 @@ struct Config {
   debug: bool,
   example_opt: Option<String>,
   export_path_opt: Option<String>,
+  pointer_width_opt: Option<String>,
+  jobs_opt: Option<String>,
+  timeout_opt: Option<String>,
 }
 
 #[derive(Debug)]
@@ -211,12 +214,35 @@ fn main() -> ! {
         .takes_value(true)
         .help("Do not verify, but only export the extracted program to the given path"),
     )
+    .arg(
+      Arg::with_name("pointer-width")
+        .long("pointer-width")
+        .takes_value(true)
+        .possible_values(&["16", "32", "64"])
+        .help("Extract isize and usize with the given width instead of the target's"),
+    )
+    .arg(
+      Arg::with_name("jobs")
+        .long("jobs")
+        .short("j")
+        .takes_value(true)
+        .help("Verify with the given number of Stainless processes in parallel"),
+    )
+    .arg(
+      Arg::with_name("timeout")
+        .long("timeout")
+        .takes_value(true)
+        .help("Give up on Stainless if it doesn't respond within the given number of seconds"),
+    )
     .get_matches();
 
   let config = Config {
     debug: matches.is_present("debug"),
     example_opt: matches.value_of("example").map(|s| s.into()),
     export_path_opt: matches.value_of("export").map(|s| s.into()),
+    pointer_width_opt: matches.value_of("pointer-width").map(|s| s.into()),
+    jobs_opt: matches.value_of("jobs").map(|s| s.into()),
+    timeout_opt: matches.value_of("timeout").map(|s| s.into()),
   };
 
   // Parse build plan
@@ -242,9 +268,27 @@ fn main() -> ! {
   if let Some(export_path) = config.export_path_opt {
     build.env.insert("RUSTSTAINLESS_EXPORT".into(), export_path);
   }
+  if let Some(pointer_width) = config.pointer_width_opt {
+    build
+      .env
+      .insert("RUSTSTAINLESS_POINTER_WIDTH".into(), pointer_width);
+  }
+
+  if let Some(jobs) = config.jobs_opt {
+    build.env.insert("RUSTSTAINLESS_JOBS".into(), jobs);
+  }
+  if let Some(timeout) = config.timeout_opt {
+    build.env.insert("RUSTSTAINLESS_TIMEOUT".into(), timeout);
+  }
 
   // Pass through certain flags
-  for &var_name in &["STAINLESS_FLAGS"] {
+  for &var_name in &[
+    "STAINLESS_FLAGS",
+    "STAINLESS_MOCK_RESPONSES",
+    "RUSTSTAINLESS_POINTER_WIDTH",
+    "RUSTSTAINLESS_JOBS",
+    "RUSTSTAINLESS_TIMEOUT",
+  ] {
     if let Ok(value) = env::var(var_name) {
       let var_name: String = var_name.into();
       build.env.entry(var_name).or_insert(value);
@@ -1,13 +1,17 @@
 #![feature(rustc_private)]
 extern crate rustc_middle;
 extern crate rustc_session;
+extern crate rustc_span;
 
+use std::collections::HashMap;
 use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use rustc_middle::ty::TyCtxt;
 use rustc_session::config::ErrorOutputType;
-use rustc_session::early_error;
+use rustc_session::{early_error, Session};
+use rustc_span::{BytePos, Span};
 
 use stainless_backend::messages::*;
 use stainless_backend::{verify_program, Config};
@@ -50,51 +54,169 @@ fn output_program<P: AsRef<std::path::Path>>(path: P, symbols: st::Symbols) {
   std::fs::write(path, ser.as_slice()).expect("Unable to write serialized stainless program");
 }
 
+/// The number of Stainless processes to verify with, as set by `RUSTSTAINLESS_JOBS`
+fn processes(sess: &Session) -> usize {
+  match env::var("RUSTSTAINLESS_JOBS") {
+    Ok(jobs) => match jobs.parse() {
+      Ok(jobs) if jobs > 0 => jobs,
+      _ => sess.fatal(&format!(
+        "Invalid number of jobs {}, expected a positive number",
+        jobs
+      )),
+    },
+    Err(_) => 1,
+  }
+}
+
+/// How long to wait for each response of Stainless, as set by `RUSTSTAINLESS_TIMEOUT` in seconds
+fn query_timeout(sess: &Session) -> Option<Duration> {
+  match env::var("RUSTSTAINLESS_TIMEOUT") {
+    Ok(secs) => match secs.parse() {
+      Ok(secs) if secs > 0 => Some(Duration::from_secs(secs)),
+      _ => sess.fatal(&format!(
+        "Invalid timeout {}, expected a positive number of seconds",
+        secs
+      )),
+    },
+    Err(_) => None,
+  }
+}
+
 fn verify_program_and_report(tcx: TyCtxt, symbols: st::Symbols) {
-  // fn build_id_map<'l>(syms: &st::Symbols<'l>) -> HashMap<usize, &'l st::SymbolIdentifier<'l>> {
-  //   let mut map = HashMap::new();
-  //   for &id in syms.sorts.keys().chain(syms.functions.keys()) {
-  //     map.insert(id.id.globalId as usize, id);
-  //   }
-  //   map
-  // }
+  /// Name functions after the class they belong to, if any, such that results for the same law
+  /// can be told apart across impls.
+  fn build_name_map<'l>(syms: &st::Symbols<'l>) -> HashMap<usize, String> {
+    syms
+      .functions
+      .values()
+      .map(|fd| {
+        let class_name = fd.flags.iter().find_map(|flag| match flag {
+          st::Flag::IsMethodOf(st::IsMethodOf { id }) => Some(&id.id.name),
+          _ => None,
+        });
+        let name = match class_name {
+          Some(class_name) => format!("{}::{}", class_name, fd.id.id.name),
+          None => fd.id.id.name.clone(),
+        };
+        (fd.id.id.globalId as usize, name)
+      })
+      .collect()
+  }
 
-  fn print_results<'l>(_tcx: TyCtxt, _symbols: &st::Symbols<'l>, results: Vec<VerificationResult>) {
-    // let id_map = build_id_map(symbols);
-    let data: Vec<Vec<String>> = results
+  /// Find the span in the local crate that a result refers to. Stainless only knows the positions
+  /// of trees it received them for, which it never does from us, so we usually fall back to the
+  /// position of the function the VC belongs to.
+  fn result_span(
+    sess: &Session,
+    symbols: &st::Symbols,
+    result: &VerificationResult,
+  ) -> Option<Span> {
+    let reported = match &result.pos.kind {
+      PositionKind::Unknown {} => None,
+      PositionKind::Offset { file, line, col } => Some((file, *line, *col, *line, *col)),
+      PositionKind::Range {
+        file,
+        line_from,
+        col_from,
+        line_to,
+        col_to,
+      } => Some((file, *line_from, *col_from, *line_to, *col_to)),
+    };
+    let definition = || {
+      [result.id.gid, result.derived_from.gid]
+        .iter()
+        .find_map(|gid| symbols.positions.get(&(*gid as i32)))
+        .map(|pos| {
+          let (line_from, col_from) = (pos.line_from as usize, pos.col_from as usize);
+          let (line_to, col_to) = (pos.line_to as usize, pos.col_to as usize);
+          (&pos.file, line_from, col_from, line_to, col_to)
+        })
+    };
+    let (file, line_from, col_from, line_to, col_to) = reported.or_else(definition)?;
+    let source_file = sess
+      .source_map()
+      .files()
       .iter()
-      .map(|result| {
-        // let id = id_map[&result.id.gid];
-        // let name = id.symbol_path.join("::");
-        let name = result.id.name.clone();
-        let time = format!("{:.1}", (result.time as f32) / 1000.0);
-        vec![name, result.kind.clone(), time, result.status.to_string()]
-      })
-      .collect();
+      .find(|source_file| source_file.name.to_string() == *file)
+      .cloned()?;
 
-    for row in data {
-      println!(
-        "- {:<38} {:^32} {:>7} {:>18}",
-        row[0], row[1], row[2], row[3]
-      );
-    }
-    println!();
+    // Columns count characters, not bytes
+    let byte_pos = |line: usize, col: usize| -> Option<BytePos> {
+      let line_index = line.checked_sub(1)?;
+      let text = source_file.get_line(line_index)?;
+      let offset = text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(text.len()))
+        .nth(col.checked_sub(1)?)?;
+      let line_start = *source_file.lines.get(line_index)?;
+      Some(line_start + BytePos(offset as u32))
+    };
+    let lo = byte_pos(line_from, col_from)?;
+    let hi = byte_pos(line_to, col_to).unwrap_or(lo);
+    Some(Span::with_root_ctxt(lo, hi))
+  }
+
+  /// Report a VC that could not be proven as an error if it's invalid, or as a warning if the
+  /// solvers gave up on it. Invalid VCs come with a counterexample, if Stainless found one.
+  fn report_result(sess: &Session, symbols: &st::Symbols, name: &str, result: &VerificationResult) {
+    let span = result_span(sess, symbols, result);
+    let mut diag = match &result.status {
+      VerificationStatus::Invalid { model } => {
+        let msg = format!(
+          "Verification failed: {} in `{}` is invalid",
+          result.kind, name
+        );
+        let mut diag = match span {
+          Some(span) => sess.struct_span_err(span, &msg),
+          None => sess.struct_err(&msg),
+        };
+        if !model.is_empty() {
+          diag.note(&format!("Counterexample: {}", model.to_rust(symbols)));
+        }
+        diag
+      }
+      _ => {
+        let msg = format!(
+          "Verification inconclusive: {} in `{}` could not be proven",
+          result.kind, name
+        );
+        match span {
+          Some(span) => sess.struct_span_warn(span, &msg),
+          None => sess.struct_warn(&msg),
+        }
+      }
+    };
+    diag.emit();
   }
 
   let sess = tcx.sess;
-  match verify_program(Config::default(), &symbols) {
+  // Check for overflows exactly when the compiled code would panic on them
+  let mut config = Config::default()
+    .with_strict_arithmetic(sess.overflow_checks())
+    .with_processes(processes(sess));
+  if let Some(timeout) = query_timeout(sess) {
+    config = config.with_query_timeout(Some(timeout));
+  }
+  match verify_program(config, &symbols) {
     Ok(Report::Verification { results, sources }) => {
       sess.note_without_error(format!("Verified {} items.", sources.len()).as_str());
-      let invalids = results
+      let name_map = build_name_map(&symbols);
+      let unproven = results
         .iter()
         .filter(|result| !result.status.is_valid())
         .collect::<Vec<_>>();
-      if invalids.is_empty() {
-        sess.note_without_error("All VCs passed!\n");
-      } else {
-        sess.warn(format!("Failed to prove {} VCs:", invalids.len()).as_str());
+      for result in &unproven {
+        let name = name_map
+          .get(&result.id.gid)
+          .cloned()
+          .unwrap_or_else(|| result.id.name.clone());
+        report_result(sess, &symbols, &name, result);
+      }
+      if unproven.is_empty() {
+        sess.note_without_error(format!("All {} VCs passed!\n", results.len()).as_str());
       }
-      print_results(tcx, &symbols, results);
+      sess.abort_if_errors();
     }
 
     Err(msg) => sess.fatal(
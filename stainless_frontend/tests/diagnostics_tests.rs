@@ -0,0 +1,89 @@
+#![feature(rustc_private)]
+extern crate stainless_frontend;
+
+use std::io::Write;
+
+pub mod utilities;
+use utilities::*;
+
+/// A response with an invalid assertion, with a counterexample, and an inconclusive
+/// postcondition, both in the function with the given id. Stainless doesn't know where our trees
+/// are, so it reports neither with a position.
+fn response(gid: i32) -> String {
+  format!(
+    r#"{{
+      "status" : "Success",
+      "reports" : [
+        {{
+          "component" : "Verification",
+          "data" : {{
+            "results" : [
+              {{
+                "id" : {{ "name" : "halve", "gid" : {0}, "id" : 0 }},
+                "pos" : {{ "kind" : {{ "Unknown" : {{}} }} }},
+                "time" : 0,
+                "status" : {{
+                  "Invalid" : {{
+                    "model" : [
+                      {{
+                        "id" : {{ "name" : "x", "gid" : 1000001, "id" : 0 }},
+                        "value" : {{
+                          "BVLiteral" : {{ "signed" : false, "value" : "0", "size" : 32 }}
+                        }}
+                      }}
+                    ]
+                  }}
+                }},
+                "solverName" : "nativez3",
+                "kind" : "body assertion",
+                "derivedFrom" : {{ "name" : "halve", "gid" : {0}, "id" : 0 }}
+              }},
+              {{
+                "id" : {{ "name" : "halve", "gid" : {0}, "id" : 0 }},
+                "pos" : {{ "kind" : {{ "Unknown" : {{}} }} }},
+                "time" : 0,
+                "status" : {{ "Inconclusive" : {{}} }},
+                "solverName" : null,
+                "kind" : "postcondition",
+                "derivedFrom" : {{ "name" : "halve", "gid" : {0}, "id" : 0 }}
+              }}
+            ],
+            "sources" : [{{ "name" : "halve", "gid" : {0}, "id" : 0 }}]
+          }}
+        }}
+      ]
+    }}"#,
+    gid
+  )
+}
+
+#[test]
+fn test_report_unproven_vcs() {
+  let test_path = manifest_relative_path("tests/fail/failing_assertion.rs");
+  let file = test_path.to_str().unwrap();
+  let gid = extracted_function_ids(&test_path)["halve"];
+  let mut responses = tempfile::NamedTempFile::new().unwrap();
+  write!(responses, "{}", response(gid)).unwrap();
+
+  let (success, stderr) = run_driver_with_responses(&test_path, responses.path());
+  assert!(!success, "{}", stderr);
+  assert!(
+    stderr.contains("error: Verification failed: body assertion in `halve` is invalid"),
+    "{}",
+    stderr
+  );
+  // Results without a position point at the function they belong to
+  assert!(stderr.contains(&format!("--> {}:4:1", file)), "{}", stderr);
+  assert!(stderr.contains("pub fn halve(x: u32) -> u32"), "{}", stderr);
+  assert!(
+    stderr.contains("= note: Counterexample: x = 0_u32"),
+    "{}",
+    stderr
+  );
+  assert!(
+    stderr
+      .contains("warning: Verification inconclusive: postcondition in `halve` could not be proven"),
+    "{}",
+    stderr
+  );
+}
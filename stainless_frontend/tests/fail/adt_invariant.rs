@@ -0,0 +1,12 @@
+extern crate stainless;
+use stainless::*;
+
+#[invariant(self.0 > 0)]
+pub struct Positive(i32);
+
+// Constructing a value that violates the invariant must be rejected.
+pub fn decrement(p: Positive) -> Positive {
+  Positive(p.0 - 1)
+}
+
+pub fn main() {}
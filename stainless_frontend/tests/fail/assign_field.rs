@@ -0,0 +1,13 @@
+extern crate stainless;
+
+// Fields need to be marked as `#[var]` to be assigned to.
+pub struct Point {
+  x: i32,
+  y: i32,
+}
+
+pub fn shift_x(p: &mut Point, s: i32) {
+  p.x = s;
+}
+
+pub fn main() {}
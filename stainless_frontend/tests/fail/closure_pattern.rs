@@ -0,0 +1,9 @@
+extern crate stainless;
+
+// Closure parameters have to be plain variables.
+pub fn sum(pair: (i32, i32)) -> i32 {
+  let add = |(a, b): (i32, i32)| a + b;
+  add(pair)
+}
+
+pub fn main() {}
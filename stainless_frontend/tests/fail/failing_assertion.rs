@@ -0,0 +1,10 @@
+extern crate stainless;
+
+// The assertion does not hold for x = 0.
+pub fn halve(x: u32) -> u32 {
+  let y = x / 2;
+  assert_ne!(y, x);
+  y
+}
+
+pub fn main() {}
@@ -0,0 +1,10 @@
+extern crate stainless;
+use stainless::*;
+
+// Laws only make sense as part of a trait.
+#[law]
+pub fn commutativity(x: i32, y: i32) -> bool {
+  x + y == y + x
+}
+
+pub fn main() {}
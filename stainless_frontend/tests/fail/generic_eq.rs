@@ -0,0 +1,8 @@
+extern crate stainless;
+
+// The `PartialEq` impl of `T` is unknown, so equality can't be assumed to be structural.
+pub fn same<T: PartialEq>(x: T, y: T) -> bool {
+  x == y
+}
+
+pub fn main() {}
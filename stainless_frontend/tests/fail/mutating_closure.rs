@@ -0,0 +1,11 @@
+extern crate stainless;
+
+pub fn count() -> i32 {
+  let mut counter = 0;
+  let mut increment = || counter += 1;
+  increment();
+  increment();
+  counter
+}
+
+pub fn main() {}
@@ -0,0 +1,15 @@
+extern crate stainless;
+use stainless::*;
+
+#[var(x)]
+pub struct Point {
+  x: i32,
+}
+
+// There is no old value to refer to in a precondition.
+#[pre(old(p.x) < 100)]
+pub fn reset(p: &mut Point) {
+  p.x = 0;
+}
+
+pub fn main() {}
@@ -0,0 +1,8 @@
+extern crate stainless;
+
+// Only the overflow-handling variants of `+`, `-`, `*`, `/`, `%` and negation are supported.
+pub fn square(x: u32) -> Option<u32> {
+  x.checked_pow(2)
+}
+
+pub fn main() {}
@@ -0,0 +1,26 @@
+extern crate stainless;
+use stainless::*;
+
+pub struct Fraction {
+  num: i32,
+  den: i32,
+}
+
+// Fractions are equal if their cross products are, which is not structural
+impl PartialEq for Fraction {
+  fn eq(&self, other: &Self) -> bool {
+    self.num * other.den == other.num * self.den
+  }
+}
+
+#[pre(a.den > 0 && a.den < 100 && a.num > 0 && a.num < 100)]
+#[post(ret)]
+pub fn halves_differ(a: Fraction) -> bool {
+  let b = Fraction {
+    num: a.num * 2,
+    den: a.den * 2,
+  };
+  a != b
+}
+
+pub fn main() {}
@@ -0,0 +1,15 @@
+extern crate stainless;
+
+// Early exits from within loops cannot be restructured into the surrounding function body.
+pub fn find_zero(n: u32) -> u32 {
+  let mut i = 0;
+  while i < n {
+    if i * i == n {
+      return i;
+    }
+    i += 1;
+  }
+  n
+}
+
+pub fn main() {}
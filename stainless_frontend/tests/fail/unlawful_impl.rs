@@ -0,0 +1,46 @@
+extern crate stainless;
+use stainless::*;
+
+pub trait Semigroup: Sized {
+  fn combine(&self, other: &Self) -> Self;
+
+  fn equiv(&self, other: &Self) -> bool;
+
+  #[law]
+  fn associativity(x: &Self, y: &Self, z: &Self) -> bool {
+    x.combine(y).combine(z).equiv(&x.combine(&y.combine(z)))
+  }
+}
+
+pub struct First {
+  value: bool,
+}
+
+impl Semigroup for First {
+  fn combine(&self, _other: &Self) -> Self {
+    First { value: self.value }
+  }
+
+  fn equiv(&self, other: &Self) -> bool {
+    self.value == other.value
+  }
+}
+
+// Implication is not associative, so this impl fails to verify.
+pub struct Implies {
+  value: bool,
+}
+
+impl Semigroup for Implies {
+  fn combine(&self, other: &Self) -> Self {
+    Implies {
+      value: !self.value || other.value,
+    }
+  }
+
+  fn equiv(&self, other: &Self) -> bool {
+    self.value == other.value
+  }
+}
+
+pub fn main() {}
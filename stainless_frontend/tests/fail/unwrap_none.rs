@@ -0,0 +1,8 @@
+extern crate stainless;
+
+// Unwrapping requires the option to hold a value, which it may not.
+pub fn first_half(x: Option<u32>) -> u32 {
+  x.unwrap() / 2
+}
+
+pub fn main() {}
@@ -0,0 +1,10 @@
+extern crate stainless;
+use std::num::Wrapping;
+
+// Compound assignments, bitwise and shift operators on `Wrapping` are not supported.
+pub fn increment(mut x: Wrapping<u8>) -> Wrapping<u8> {
+  x += Wrapping(1);
+  x
+}
+
+pub fn main() {}
@@ -0,0 +1,41 @@
+extern crate stainless;
+use stainless::*;
+
+#[invariant(self.lo <= self.hi)]
+pub struct Interval {
+  lo: i32,
+  hi: i32,
+}
+
+impl Interval {
+  // The invariant is assumed for the parameter...
+  #[post(ret >= 0)]
+  pub fn width(&self) -> i64 {
+    self.hi as i64 - self.lo as i64
+  }
+
+  // ...and checked for the constructed value
+  #[pre(x > 0)]
+  pub fn around(x: i32) -> Interval {
+    Interval { lo: x - 1, hi: x }
+  }
+}
+
+#[invariant(match self { Shape::Square(side) => *side > 0, Shape::Point => true })]
+pub enum Shape {
+  Point,
+  Square(u32),
+}
+
+pub fn area(shape: Shape) -> u64 {
+  match shape {
+    Shape::Point => 0,
+    Shape::Square(side) => side as u64 * side as u64,
+  }
+}
+
+pub fn main() {
+  let i = Interval::around(2);
+  i.width();
+  area(Shape::Square(3));
+}
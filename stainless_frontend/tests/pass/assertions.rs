@@ -0,0 +1,18 @@
+extern crate stainless;
+use stainless::*;
+
+pub fn double(x: u32) -> u32 {
+  assume!(x < 1000);
+  let y = x + x;
+  assert!(y >= x);
+  assert!(y < 2000, "y is small");
+  assert_eq!(y, 2 * x);
+  assert_ne!(y, x + 1, "y is even");
+  debug_assert!(y % 2 == 0);
+  debug_assert_eq!(y / 2, x);
+  y
+}
+
+pub fn main() {
+  double(21);
+}
@@ -0,0 +1,43 @@
+extern crate stainless;
+use stainless::*;
+
+#[post(ret >= 1.into())]
+pub fn fact(x: BigInt) -> BigInt {
+  if x <= 0.into() {
+    1.into()
+  } else {
+    fact(x.clone() - 1) * x
+  }
+}
+
+#[post(ret >= BigInt::from(0) && (ret == a.clone() || ret == -a.clone()))]
+pub fn abs(a: BigInt) -> BigInt {
+  if a < 0.into() {
+    -a
+  } else {
+    a
+  }
+}
+
+#[pre(b != 0.into())]
+#[post(ret.clone() * b.clone() + a.clone() % b.clone() == a)]
+pub fn div(a: BigInt, b: BigInt) -> BigInt {
+  a / b
+}
+
+#[post(ret > a)]
+pub fn succ(a: BigInt) -> BigInt {
+  a + BigInt::from(1)
+}
+
+#[post(ret < a)]
+pub fn pred(a: BigInt) -> BigInt {
+  a + BigInt::from(-1)
+}
+
+#[post(ret > a)]
+pub fn add_two(a: BigInt) -> BigInt {
+  a - -2
+}
+
+pub fn main() {}
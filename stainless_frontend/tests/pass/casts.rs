@@ -0,0 +1,47 @@
+extern crate stainless;
+use stainless::*;
+
+pub enum Level {
+  Low = 1,
+  Mid = 5,
+  High = -3,
+}
+
+pub fn widening(x: u8, y: i8) {
+  assert!(x as u32 <= 255);
+  assert!(y as i64 >= -128 && y as i64 <= 127);
+  assert!((x as u16) * 2 == (x as u16) + (x as u16));
+}
+
+pub fn narrowing(x: u32, y: i64) {
+  assert!(x as u8 == (x & 255) as u8);
+  if y >= -128 && y <= 127 {
+    assert!((y as i8) as i64 == y);
+  }
+}
+
+pub fn sign_changes(x: i8, y: u16) {
+  assert!(x >= 0 || x as u8 >= 128);
+  assert!((x as u8) as i8 == x);
+  assert!(y < 32768 || (y as i16) < 0);
+  // Sign-extend first, then reinterpret
+  assert!(x >= 0 || x as u32 >= 4294967168);
+  assert!(y as i32 >= 0);
+}
+
+#[post(ret <= 1)]
+pub fn from_bool(b: bool) -> u32 {
+  b as u32
+}
+
+#[post(ret == 1 || ret == 5 || ret == -3)]
+pub fn level_code(level: Level) -> i32 {
+  level as i32
+}
+
+#[post(ret == 253 || ret < 6)]
+pub fn level_byte(level: Level) -> u8 {
+  level as u8
+}
+
+pub fn main() {}
@@ -0,0 +1,48 @@
+extern crate stainless;
+use stainless::*;
+
+pub enum List<T> {
+  Nil,
+  Cons(T, Box<List<T>>),
+}
+
+pub fn map<T, U, F: Fn(T) -> U>(list: List<T>, f: F) -> List<U> {
+  match list {
+    List::Nil => List::Nil,
+    List::Cons(head, tail) => List::Cons(f(head), Box::new(map(*tail, f))),
+  }
+}
+
+pub fn fold<T, A, F: Fn(A, T) -> A>(list: List<T>, acc: A, f: F) -> A {
+  match list {
+    List::Nil => acc,
+    List::Cons(head, tail) => fold(*tail, f(acc, head), f),
+  }
+}
+
+#[post(ret == 2 * x + 1)]
+pub fn apply_twice_plus_one(x: i32) -> i32 {
+  let double = |y: i32| y * 2;
+  double(x) + 1
+}
+
+#[post(ret == x.wrapping_add(y))]
+pub fn add_with_closure(x: i32, y: i32) -> i32 {
+  let add = |a: i32, b: i32| a.wrapping_add(b);
+  add(x, y)
+}
+
+#[post(ret == 7)]
+pub fn sum_after_offset() -> i32 {
+  let offset = 1;
+  let list = List::Cons(2, Box::new(List::Cons(3, Box::new(List::Nil))));
+  let shifted = map(list, |x| x + offset);
+  fold(shifted, 0, |acc, x| acc + x)
+}
+
+#[post(ret.is_some() == opt.is_some())]
+pub fn increment(opt: Option<i32>, by: i32) -> Option<i32> {
+  opt.map(|x| x.wrapping_add(by))
+}
+
+pub fn main() {}
@@ -0,0 +1,69 @@
+extern crate stainless;
+use stainless::*;
+
+pub enum ParseError {
+  Empty,
+  Negative,
+}
+
+#[post(ret >= 0)]
+pub fn abs(x: i32) -> i32 {
+  if x >= 0 {
+    return x;
+  }
+  if x <= -1000 {
+    return 0;
+  }
+  -x
+}
+
+pub fn first_positive(a: i32, b: i32) -> Option<i32> {
+  if a > 0 {
+    return Some(a);
+  } else if b > 0 {
+    return Some(b);
+  }
+  None
+}
+
+pub fn add_options(a: Option<i32>, b: Option<i32>) -> Option<i32> {
+  let x = a?;
+  let y = b?;
+  if x < 1000 && y < 1000 && x > -1000 && y > -1000 {
+    Some(x + y)
+  } else {
+    None
+  }
+}
+
+pub fn check(x: i32) -> Result<i32, ParseError> {
+  match x {
+    0 => Err(ParseError::Empty),
+    x if x < 0 => Err(ParseError::Negative),
+    x => Ok(x),
+  }
+}
+
+pub fn halve(x: i32) -> Result<i32, ParseError> {
+  Ok(check(x)? / 2)
+}
+
+pub fn sum_checked(x: i32, y: i32) -> Result<i32, ParseError> {
+  if check(x)? > 1000 || y > 1000 {
+    return Ok(0);
+  }
+  Ok(x + check(y)?)
+}
+
+// The assumption rules out an overflow after the early return
+#[post(ret >= 0)]
+pub fn clamped_double(x: i32) -> i32 {
+  assume!(x < 1000);
+  if x < 0 {
+    return 0;
+  }
+  assert!(x >= 0);
+  x * 2
+}
+
+pub fn main() {}
@@ -0,0 +1,52 @@
+extern crate stainless;
+use stainless::*;
+
+#[derive(PartialEq)]
+pub enum Color {
+  Red,
+  Green,
+  Blue,
+}
+
+#[derive(PartialEq)]
+pub struct Point {
+  x: i32,
+  y: i32,
+}
+
+#[derive(PartialEq)]
+pub enum List<T> {
+  Nil,
+  Cons(T, Box<List<T>>),
+}
+
+#[post(ret == (c == Color::Red))]
+pub fn is_red(c: Color) -> bool {
+  match c {
+    Color::Red => true,
+    _ => false,
+  }
+}
+
+#[post(ret)]
+pub fn point_eq(x: i32, y: i32) -> bool {
+  Point { x, y } == Point { x, y }
+}
+
+#[post(ret == (a.x != b.x || a.y != b.y))]
+pub fn point_ne(a: &Point, b: &Point) -> bool {
+  a != b
+}
+
+#[post(ret == (p.0 == q.0 && p.1 == q.1))]
+pub fn tuple_eq(p: (i32, bool), q: (i32, bool)) -> bool {
+  p == q
+}
+
+#[post(ret)]
+pub fn list_eq(x: i32) -> bool {
+  let l = List::Cons(x, Box::new(List::Nil));
+  l.eq(&l)
+}
+
+pub fn main() {}
@@ -0,0 +1,44 @@
+extern crate stainless;
+use stainless::*;
+
+#[pre(n >= 0 && n < 1000)]
+#[post(ret == 2 * n)]
+pub fn double(n: i32) -> i32 {
+  let mut acc = 0;
+  for i in 0..n {
+    loop_invariant!(i >= 0 && i <= n && acc == 2 * i);
+    acc += 2;
+  }
+  acc
+}
+
+#[pre(n < 100)]
+#[post(ret == n + 1)]
+pub fn count_inclusive(n: u32) -> u32 {
+  let mut count = 0;
+  for i in 0..=n {
+    loop_invariant!(i <= n + 1 && count == i);
+    count += 1;
+  }
+  count
+}
+
+pub fn flip_n_times(n: u32, flag: bool) -> bool {
+  let mut flag = flag;
+  for _ in 0..n {
+    flag = !flag;
+  }
+  flag
+}
+
+/// The counter of an inclusive range that ends at the maximum value of its type (here
+/// `u8::MAX`) must not be incremented past the end.
+pub fn contains_byte(byte: u8) -> bool {
+  let mut found = false;
+  for i in 0..=255 {
+    if i == byte {
+      found = true;
+    }
+  }
+  found
+}
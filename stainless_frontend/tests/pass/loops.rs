@@ -0,0 +1,41 @@
+extern crate stainless;
+use stainless::*;
+
+#[pre(n >= 0 && n < 1000)]
+#[post(ret == n)]
+pub fn count_up(n: i32) -> i32 {
+  let mut i = 0;
+  while i < n {
+    loop_invariant!(i >= 0 && i <= n);
+    i += 1;
+  }
+  i
+}
+
+#[pre(n >= 0 && n < 1000)]
+#[post(ret == 2 * n)]
+pub fn double(n: i32) -> i32 {
+  let mut i = 0;
+  let mut acc = 0;
+  while i < n {
+    loop_invariant!(i >= 0 && i <= n && acc == 2 * i);
+    i += 1;
+    acc += 2;
+  }
+  acc
+}
+
+#[pre(n >= 0 && n < 1000)]
+#[post(ret >= 0 && ret <= n)]
+pub fn count_up_with_break(n: i32) -> i32 {
+  let mut i = 0;
+  loop {
+    loop_invariant!(i >= 0 && i <= n);
+    if i == n {
+      break;
+    } else {
+      i += 1;
+    }
+  }
+  i
+}
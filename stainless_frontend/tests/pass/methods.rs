@@ -0,0 +1,53 @@
+extern crate stainless;
+use stainless::*;
+
+pub struct Foo;
+
+impl Foo {
+  pub fn new() -> Self {
+    Self
+  }
+
+  pub fn get(&self) -> i32 {
+    1
+  }
+}
+
+pub struct Counter {
+  value: i32,
+}
+
+impl Counter {
+  #[pre(value >= 0)]
+  #[post(ret.value == value)]
+  pub fn new(value: i32) -> Self {
+    Counter { value }
+  }
+
+  #[post(ret == self.value)]
+  pub fn get(&self) -> i32 {
+    self.value
+  }
+
+  #[pre(self.value < 1000)]
+  #[post(ret.value == self.value + 1)]
+  pub fn incremented(self) -> Counter {
+    Counter {
+      value: self.value + 1,
+    }
+  }
+}
+
+#[post(ret == 1)]
+pub fn f() -> i32 {
+  let foo = Foo::new();
+  foo.get()
+}
+
+#[post(ret == 2)]
+pub fn g() -> i32 {
+  let counter = Counter::new(1).incremented();
+  Counter::get(&counter)
+}
+
+pub fn main() {}
@@ -1,18 +1,15 @@
 extern crate stainless;
+use stainless::*;
 
 pub enum IntOption {
   None,
   Some(i32),
 }
 
-/// This spec should always fail because some things are immutable.
-/// rust-stainless builds upon the fact that there is no immutability for the
-/// moment.
 pub fn main() {
-  // This should fail because of the mut binding.
   let mut x = 1;
-  println!("x = {}", x);
   x = 3;
+  assert!(x == 3);
 
   let option = IntOption::Some(2);
   let mut option2 = IntOption::None;
@@ -21,3 +18,15 @@ pub fn main() {
   option2 = option;
   option = IntOption::Some(123)
 }
+
+#[pre(x >= 0 && x < 100)]
+#[post(ret == 2 * x + 10)]
+pub fn compound_assignments(x: i32) -> i32 {
+  let mut acc = x;
+  acc += x;
+  acc *= 2;
+  acc -= x;
+  acc -= x;
+  acc += 10;
+  acc
+}
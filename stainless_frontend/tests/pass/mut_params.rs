@@ -0,0 +1,24 @@
+extern crate stainless;
+use stainless::*;
+
+#[pre(n <= 10)]
+#[post(ret == 0)]
+pub fn count_down(mut n: u32) -> u32 {
+  while n > 0 {
+    n -= 1;
+  }
+  n
+}
+
+#[pre(x > -1000 && x < 1000)]
+#[post(ret >= 0 && (ret == x || ret == -x))]
+pub fn abs(mut x: i64) -> i64 {
+  if x < 0 {
+    x = -x;
+  }
+  x
+}
+
+pub fn main() {
+  assert!(count_down(5) == 0);
+}
@@ -0,0 +1,62 @@
+extern crate stainless;
+use stainless::*;
+
+#[var(x, y)]
+pub struct Point {
+  x: i32,
+  y: i32,
+}
+
+#[post(p.x == x)]
+pub fn set_x(p: &mut Point, x: i32) {
+  p.x = x;
+}
+
+#[post(p.x == q.x && p.y == q.y)]
+pub fn overwrite(p: &mut Point, q: Point) {
+  *p = q;
+}
+
+impl Point {
+  pub fn swap(&mut self) {
+    let x = self.x;
+    self.x = self.y;
+    self.y = x;
+  }
+}
+
+#[var(0)]
+pub struct Counter(u32);
+
+#[pre(c.0 < 100)]
+pub fn increment(c: &mut Counter) {
+  c.0 += 1;
+}
+
+#[var(value)]
+#[mutable(T)]
+pub struct Cell<T> {
+  value: T,
+}
+
+pub fn replace<T>(cell: &mut Cell<T>, value: T) {
+  cell.value = value;
+}
+
+#[post(ret == 2)]
+pub fn aligned() -> i32 {
+  let mut p = Point { x: 1, y: 2 };
+  let y = p.y;
+  set_x(&mut p, y);
+  overwrite(&mut p, Point { x: 2, y: 1 });
+  p.swap();
+
+  let mut c = Counter(0);
+  increment(&mut c);
+
+  let mut cell = Cell { value: 0 };
+  replace(&mut cell, 1);
+  2
+}
+
+pub fn main() {}
@@ -0,0 +1,42 @@
+extern crate stainless;
+use stainless::*;
+
+#[var(x, y)]
+pub struct Point {
+  x: i32,
+  y: i32,
+}
+
+#[pre(p.x >= 0 && p.x < 1000 && s >= 0 && s < 1000)]
+#[post(p.x == old(p.x) + s && p.y == old(p.y))]
+pub fn shift_x(p: &mut Point, s: i32) {
+  p.x = p.x + s;
+}
+
+impl Point {
+  #[post(self.x == old(self.y) && self.y == old(self.x))]
+  pub fn swap(&mut self) {
+    let x = self.x;
+    self.x = self.y;
+    self.y = x;
+  }
+}
+
+pub struct Pair(Point, Point);
+
+// `Point` isn't `Copy`, but the spec macros borrow old values rather than moving them
+#[post(pair.0.x == old(pair.1).x && pair.1.x == old(pair.0).x)]
+pub fn swap_xs(pair: &mut Pair) {
+  let x = pair.0.x;
+  pair.0.x = pair.1.x;
+  pair.1.x = x;
+}
+
+#[post(ret == 3)]
+pub fn shifted() -> i32 {
+  let mut p = Point { x: 1, y: 2 };
+  shift_x(&mut p, 2);
+  p.x
+}
+
+pub fn main() {}
@@ -0,0 +1,66 @@
+extern crate stainless;
+use stainless::*;
+
+use std::num::Wrapping;
+
+pub fn wrapping(x: u8, y: i32) {
+  assert!(x.wrapping_add(1) == 0 || x.wrapping_add(1) > x);
+  assert!(255u8.wrapping_add(1) == 0);
+  assert!(y.wrapping_sub(y) == 0);
+  assert!((-128i8).wrapping_mul(-1) == -128);
+}
+
+pub fn checked(x: u8, y: i16) {
+  assert!(x.checked_sub(x) == Some(0));
+  assert!(0u8.checked_sub(1).is_none());
+  assert!(x.checked_add(1).is_none() == (x == 255));
+  assert!(y.checked_mul(2).is_some() == (y >= -16384 && y <= 16383));
+}
+
+#[post(ret <= 100)]
+pub fn saturating(x: u32) -> u32 {
+  x.saturating_sub(4_000_000_000)
+    .saturating_mul(1_000)
+    .saturating_add(0)
+    / 42_949_673
+}
+
+pub fn saturating_signed(x: i8) {
+  assert!(x.saturating_add(100) >= x || x.saturating_add(100) == 127);
+  assert!(i8::saturating_sub(-100, 100) == -128);
+}
+
+pub fn overflowing(x: u16) {
+  let (sum, overflowed) = x.overflowing_add(1);
+  assert!(overflowed == (x == 65535));
+  assert!(overflowed || sum == x + 1);
+}
+
+pub fn division(x: i8, y: u8) {
+  assert!(x == -128 || x.wrapping_div(-1) == -x);
+  assert!((-128i8).wrapping_div(-1) == -128);
+  assert!((-128i8).wrapping_rem(-1) == 0);
+  assert!(x.checked_div(0).is_none());
+  assert!(y.checked_rem(3) == Some(y % 3));
+  let (quotient, overflowed) = x.overflowing_div(-1);
+  assert!(overflowed == (x == -128) && (overflowed || quotient == -x));
+}
+
+pub fn negation(x: i16, y: u16) {
+  assert!(x == -32768 || x.wrapping_neg() == -x);
+  assert!((-32768i16).checked_neg().is_none());
+  assert!(y.checked_neg().is_some() == (y == 0));
+  let (negated, overflowed) = y.overflowing_neg();
+  assert!(overflowed == (y != 0) && negated == 0u16.wrapping_sub(y));
+  assert!(x == -32768 || x.saturating_neg() == -x);
+  assert!((-32768i16).saturating_neg() == 32767);
+}
+
+#[post(ret.0 == 0)]
+pub fn counter(start: Wrapping<u8>) -> Wrapping<u8> {
+  let one = Wrapping(1u8);
+  let end = start + (Wrapping(255) - start) + one;
+  -(end / one)
+}
+
+pub fn main() {}
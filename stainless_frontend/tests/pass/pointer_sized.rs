@@ -0,0 +1,36 @@
+extern crate stainless;
+use stainless::*;
+
+#[pre(len > 0 && i < len)]
+#[post(ret < len)]
+pub fn next_index(i: usize, len: usize) -> usize {
+  if i + 1 == len {
+    0
+  } else {
+    i + 1
+  }
+}
+
+#[pre(lo <= hi)]
+#[post(lo <= ret && ret <= hi)]
+pub fn midpoint(lo: usize, hi: usize) -> usize {
+  lo + (hi - lo) / 2
+}
+
+#[post(ret >= 0)]
+pub fn distance(a: isize, b: isize) -> isize {
+  if a < -1000 || a > 1000 || b < -1000 || b > 1000 {
+    0
+  } else if a < b {
+    b - a
+  } else {
+    a - b
+  }
+}
+
+pub fn widths(x: u32, y: usize) {
+  assert!(x as usize as u32 == x);
+  assert!(y as u64 as usize == y);
+}
+
+pub fn main() {}
@@ -0,0 +1,25 @@
+extern crate stainless;
+use stainless::*;
+
+#[post(forall(|i: u32| !(i < n) || ret > i))]
+pub fn above(n: u32) -> u32 {
+  n
+}
+
+#[pre(forall(|x: i32| f(x) >= x))]
+#[post(ret >= 0)]
+pub fn at_zero<F: Fn(i32) -> i32>(f: F) -> i32 {
+  f(0)
+}
+
+#[post(exists(|y: u8| ret == y / 2))]
+pub fn half(x: u8) -> u8 {
+  x / 2
+}
+
+#[post(forall(|a: bool, b: bool| !(a && b) || ret))]
+pub fn always() -> bool {
+  true
+}
+
+pub fn main() {}
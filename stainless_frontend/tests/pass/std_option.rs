@@ -0,0 +1,82 @@
+extern crate stainless;
+use stainless::*;
+
+pub enum Error {
+  Missing,
+  Invalid,
+}
+
+pub fn lookup(key: i32) -> Option<i32> {
+  if key >= 0 && key < 100 {
+    Some(key * 2)
+  } else {
+    None
+  }
+}
+
+#[pre(key >= 0 && key < 100)]
+#[post(ret == key * 2)]
+pub fn lookup_valid(key: i32) -> i32 {
+  lookup(key).unwrap()
+}
+
+#[post(ret >= 0)]
+pub fn lookup_or_zero(key: i32) -> i32 {
+  lookup(key).unwrap_or(0)
+}
+
+#[post(ret.is_ok() == lookup(key).is_some())]
+pub fn lookup_result(key: i32) -> Result<i32, Error> {
+  lookup(key).ok_or(Error::Missing)
+}
+
+pub fn validate(value: i32) -> Result<i32, Error> {
+  if value < 0 {
+    Err(Error::Invalid)
+  } else {
+    Ok(value)
+  }
+}
+
+#[post(ret.is_some() == (value >= 0))]
+pub fn validate_opt(value: i32) -> Option<i32> {
+  validate(value).ok()
+}
+
+#[post(ret == (value < 0))]
+pub fn is_invalid(value: i32) -> bool {
+  validate(value).is_err() && validate(value).err().is_some()
+}
+
+pub fn first_or_second(a: Option<i32>, b: Option<i32>) -> Option<i32> {
+  a.or(b)
+}
+
+#[post(ret.is_some() == lookup(key).is_some())]
+pub fn lookup_halved(key: i32) -> Option<i32> {
+  lookup(key).map(|value| value / 2)
+}
+
+#[post(!ret.is_some() || lookup(key).is_some())]
+pub fn lookup_twice(key: i32) -> Option<i32> {
+  lookup(key).and_then(|value| lookup(value))
+}
+
+#[post(!ret.is_some() || lookup(key).is_some())]
+pub fn lookup_multiple_of_four(key: i32) -> Option<i32> {
+  lookup(key).filter(|value| *value % 4 == 0)
+}
+
+pub fn error_code(error: Error) -> i32 {
+  match error {
+    Error::Missing => 1,
+    Error::Invalid => 2,
+  }
+}
+
+#[post(ret.is_ok() == validate(value).is_ok())]
+pub fn validate_with_code(value: i32) -> Result<i32, i32> {
+  validate(value).map_err(|error| error_code(error))
+}
+
+pub fn main() {}
@@ -0,0 +1,60 @@
+extern crate stainless;
+use stainless::*;
+
+pub trait Semigroup: Sized {
+  fn combine(&self, other: &Self) -> Self;
+
+  fn equiv(&self, other: &Self) -> bool;
+
+  #[law]
+  fn reflexivity(x: &Self) -> bool {
+    x.equiv(x)
+  }
+
+  #[law]
+  fn associativity(x: &Self, y: &Self, z: &Self) -> bool {
+    x.combine(y).combine(z).equiv(&x.combine(&y.combine(z)))
+  }
+}
+
+pub struct Max {
+  value: i32,
+}
+
+impl Semigroup for Max {
+  fn combine(&self, other: &Self) -> Self {
+    if self.value >= other.value {
+      Max { value: self.value }
+    } else {
+      Max { value: other.value }
+    }
+  }
+
+  fn equiv(&self, other: &Self) -> bool {
+    self.value == other.value
+  }
+}
+
+pub struct Or {
+  value: bool,
+}
+
+impl Semigroup for Or {
+  fn combine(&self, other: &Self) -> Self {
+    Or {
+      value: self.value || other.value,
+    }
+  }
+
+  fn equiv(&self, other: &Self) -> bool {
+    self.value == other.value
+  }
+
+  // Overriding a law is allowed, the trait's version still has to hold
+  #[law]
+  fn reflexivity(x: &Self) -> bool {
+    x.value == x.value
+  }
+}
+
+pub fn main() {}
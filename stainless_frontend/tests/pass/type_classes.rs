@@ -0,0 +1,68 @@
+extern crate stainless;
+use stainless::*;
+
+pub trait Monoid: Sized {
+  fn empty() -> Self;
+
+  fn combine(&self, other: &Self) -> Self;
+
+  fn combine_empty(&self) -> Self {
+    self.combine(&Self::empty())
+  }
+}
+
+pub struct Any {
+  value: bool,
+}
+
+impl Monoid for Any {
+  fn empty() -> Self {
+    Any { value: false }
+  }
+
+  fn combine(&self, other: &Self) -> Self {
+    Any {
+      value: self.value || other.value,
+    }
+  }
+}
+
+pub struct Pair<A, B> {
+  first: A,
+  second: B,
+}
+
+impl<A: Monoid, B: Monoid> Monoid for Pair<A, B> {
+  fn empty() -> Self {
+    Pair {
+      first: A::empty(),
+      second: B::empty(),
+    }
+  }
+
+  fn combine(&self, other: &Self) -> Self {
+    Pair {
+      first: self.first.combine(&other.first),
+      second: self.second.combine(&other.second),
+    }
+  }
+}
+
+pub fn combine_three<T: Monoid>(a: &T, b: &T, c: &T) -> T {
+  a.combine(b).combine(c)
+}
+
+pub fn combine_four<T: Monoid>(a: &T, b: &T, c: &T, d: &T) -> T {
+  combine_three(a, b, c).combine(d)
+}
+
+pub fn any_pairs() -> Pair<Any, Any> {
+  let a = Pair {
+    first: Any { value: true },
+    second: Any::empty(),
+  };
+  let b = Pair::empty();
+  combine_four(&a, &b, &a.combine_empty(), &b)
+}
+
+pub fn main() {}
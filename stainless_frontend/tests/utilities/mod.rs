@@ -1,5 +1,7 @@
+use std::collections::HashMap;
 use std::env;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use stainless_backend::messages::Report;
 use stainless_backend::{verify_program, Config};
@@ -12,8 +14,11 @@ pub enum Outcome {
   ErrorInExtraction,
   CrashInVerification,
   ErrorInVerification,
+  VerificationSkipped,
 }
 
+/// Extract the given test case and, if asked to, verify it. Verification is skipped without a
+/// Stainless installation.
 pub fn run_extraction_test<S: AsRef<Path>>(source_path: S, verify: bool) -> Outcome {
   let args = compiler_args(source_path);
 
@@ -34,11 +39,26 @@ pub fn run_extraction_test<S: AsRef<Path>>(source_path: S, verify: bool) -> Outc
 }
 
 fn run_verification_test(symbols: &st::Symbols) -> Outcome {
+  if env::var("STAINLESS_HOME").is_err() {
+    return Outcome::VerificationSkipped;
+  }
   if let Ok(report) = verify_program(Config::default(), symbols) {
-    let all_valid = match report {
-      Report::Verification { results, .. } => results.iter().all(|result| result.status.is_valid()),
+    let (all_valid, sources) = match report {
+      Report::Verification { results, sources } => (
+        results.iter().all(|result| result.status.is_valid()),
+        sources,
+      ),
+    };
+    // Stainless must only report on functions we extracted
+    let is_extracted = |gid: usize| {
+      symbols
+        .functions
+        .keys()
+        .any(|id| id.id.globalId as usize == gid)
     };
-    if all_valid {
+    if !sources.iter().all(|source| is_extracted(source.gid)) {
+      Outcome::CrashInVerification
+    } else if all_valid {
       Outcome::Success { verified: true }
     } else {
       Outcome::ErrorInVerification
@@ -54,6 +74,39 @@ pub fn manifest_relative_path<S: AsRef<Path>>(relative_path: S) -> PathBuf {
     .join(relative_path)
 }
 
+/// The global ids of the functions extracted from a test case, by their names. Extraction is
+/// deterministic, so these are also the ids the driver extracts the test case with.
+pub fn extracted_function_ids<S: AsRef<Path>>(source_path: S) -> HashMap<String, i32> {
+  let mut ids = HashMap::new();
+  let _ = stainless_frontend::run(compiler_args(source_path), |_, symbols| {
+    ids = symbols
+      .functions
+      .keys()
+      .map(|id| (id.id.name.clone(), id.id.globalId))
+      .collect();
+  });
+  ids
+}
+
+/// Run the `rustc_to_stainless` driver on a test case, replaying the given canned responses
+/// instead of querying Stainless. Returns whether the driver succeeded, and what it printed.
+pub fn run_driver_with_responses<S, R>(source_path: S, responses_path: R) -> (bool, String)
+where
+  S: AsRef<Path>,
+  R: AsRef<Path>,
+{
+  // The first argument only stands in for the name of the binary
+  let args = compiler_args(source_path);
+  let output = Command::new(env!("CARGO_BIN_EXE_rustc_to_stainless"))
+    .args(&args[1..])
+    .env("STAINLESS_MOCK_RESPONSES", responses_path.as_ref())
+    .env_remove("RUSTSTAINLESS_EXPORT")
+    .output()
+    .expect("Failed to run rustc_to_stainless");
+  let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+  (output.status.success(), stderr)
+}
+
 /// Helpers
 
 fn _find_tests() -> Vec<PathBuf> {